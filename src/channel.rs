@@ -0,0 +1,148 @@
+use {
+    crate::Queue,
+    std::sync::{Arc, Condvar, Mutex, MutexGuard},
+};
+
+struct State<T, const N: usize> {
+    queue: Queue<T, N>,
+    /// Set when either half is dropped
+    closed: bool,
+}
+
+struct Shared<T, const N: usize> {
+    state: Mutex<State<T, N>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl<T, const N: usize> Shared<T, N> {
+    fn lock(&self) -> MutexGuard<'_, State<T, N>> {
+        // The queue is never left in an inconsistent state, so a poisoned lock is still usable
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn close(&self) {
+        self.lock().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+}
+
+/// Create a channel backed by a [`Queue`] with room for `N` items.
+/// Unlike [`Queue::split`], the halves can be sent to other threads.
+pub fn bounded<T, const N: usize>() -> (Sender<T, N>, Receiver<T, N>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: Queue::new(),
+            closed: false,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+/// Sending half of a channel, created with [`bounded`].
+pub struct Sender<T, const N: usize> {
+    shared: Arc<Shared<T, N>>,
+}
+
+impl<T, const N: usize> Sender<T, N> {
+    /// Send an item, blocking while the channel is full.
+    /// Hands the item back if the receiver has been dropped.
+    pub fn send(&self, item: T) -> Result<(), T> {
+        let mut state = self.shared.lock();
+        while state.queue.is_full() && !state.closed {
+            state = self
+                .shared
+                .not_full
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        if state.closed {
+            return Err(item);
+        }
+        // Can't fail, the queue isn't full
+        let _ = state.queue.push(item);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Drop for Sender<T, N> {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+/// Receiving half of a channel, created with [`bounded`].
+pub struct Receiver<T, const N: usize> {
+    shared: Arc<Shared<T, N>>,
+}
+
+impl<T, const N: usize> Receiver<T, N> {
+    /// Receive an item, blocking while the channel is empty.
+    /// Returns `None` once the sender has been dropped and all items have been received.
+    pub fn recv(&self) -> Option<T> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(item) = state.queue.pop() {
+                self.shared.not_full.notify_one();
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .shared
+                .not_empty
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for Receiver<T, N> {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::thread};
+
+    #[test]
+    fn threads() {
+        let (tx, rx) = bounded::<u32, 4>();
+        let producer = thread::spawn(move || {
+            for i in 0..1000 {
+                assert_eq!(tx.send(i), Ok(()));
+            }
+        });
+        let consumer = thread::spawn(move || {
+            let mut received = 0;
+            while let Some(i) = rx.recv() {
+                assert_eq!(i, received);
+                received += 1;
+            }
+            received
+        });
+
+        producer.join().unwrap();
+        assert_eq!(consumer.join().unwrap(), 1000);
+    }
+
+    #[test]
+    fn receiver_dropped() {
+        let (tx, rx) = bounded::<u32, 1>();
+        assert_eq!(tx.send(1), Ok(()));
+        drop(rx);
+        assert_eq!(tx.send(2), Err(2));
+    }
+}
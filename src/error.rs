@@ -1,6 +1,40 @@
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 #[non_exhaustive]
 pub enum Error {
     Full = 1,
+    Empty = 2,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Full => f.write_str("container is full"),
+            Self::Empty => f.write_str("container is empty"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::string::ToString};
+
+    #[test]
+    fn display() {
+        assert_eq!(Error::Full.to_string(), "container is full");
+        assert_eq!(Error::Empty.to_string(), "container is empty");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn boxed() {
+        fn push() -> Result<(), std::boxed::Box<dyn std::error::Error>> {
+            Err(Error::Full)?
+        }
+        assert_eq!(push().unwrap_err().to_string(), "container is full");
+    }
 }
@@ -0,0 +1,34 @@
+/// Byte order of the `u32`s making up the [`Kv`](super::Kv) header and value headers.
+/// Value data is always stored as-is.
+pub trait ByteOrder {
+    fn from_bytes(bytes: [u8; 4]) -> u32;
+    fn to_bytes(value: u32) -> [u8; 4];
+}
+
+/// Little-endian byte order, the default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LittleEndian;
+
+impl ByteOrder for LittleEndian {
+    fn from_bytes(bytes: [u8; 4]) -> u32 {
+        u32::from_le_bytes(bytes)
+    }
+
+    fn to_bytes(value: u32) -> [u8; 4] {
+        value.to_le_bytes()
+    }
+}
+
+/// Big-endian byte order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BigEndian;
+
+impl ByteOrder for BigEndian {
+    fn from_bytes(bytes: [u8; 4]) -> u32 {
+        u32::from_be_bytes(bytes)
+    }
+
+    fn to_bytes(value: u32) -> [u8; 4] {
+        value.to_be_bytes()
+    }
+}
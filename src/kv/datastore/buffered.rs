@@ -0,0 +1,243 @@
+use super::super::KvDataAccess;
+
+/// A write-back cache in front of another datastore. Writes to the same `PAGE` sized, page aligned
+/// block are coalesced in RAM and written to the store when another page is written to, or on `flush`.
+///
+/// Unflushed writes are lost if the wrapper is dropped, use `into_inner` or `KvDataAccess::flush` first.
+#[derive(Debug, Clone)]
+pub struct Buffered<S, const PAGE: usize> {
+    store: S,
+    page: [u8; PAGE],
+    /// Address of the cached page
+    base: u32,
+    /// Range of the page that hasn't been written to the store
+    dirty: Option<(usize, usize)>,
+}
+
+impl<S: KvDataAccess, const PAGE: usize> Buffered<S, PAGE> {
+    pub const fn new(store: S) -> Self {
+        assert!(PAGE > 0);
+        Self {
+            store,
+            page: [0; PAGE],
+            base: 0,
+            dirty: None,
+        }
+    }
+
+    /// Write any cached data to the store
+    fn write_back(&mut self) -> Result<(), S::Error> {
+        if let Some((lo, hi)) = self.dirty {
+            self.store
+                .write_exact(self.base + lo as u32, &self.page[lo..hi])?;
+            self.dirty = None;
+        }
+        Ok(())
+    }
+
+    /// The underlying store, without any unflushed writes
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    /// Flush and return the underlying store
+    pub fn into_inner(mut self) -> Result<S, S::Error> {
+        self.flush()?;
+        Ok(self.store)
+    }
+}
+
+impl<S: KvDataAccess, const PAGE: usize> KvDataAccess for Buffered<S, PAGE> {
+    type Error = S::Error;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        let start = address as usize;
+        let end = start + dst.len();
+        let base = self.base as usize;
+        match self.dirty {
+            Some((lo, hi)) if base + lo <= start && end <= base + hi => {
+                dst.copy_from_slice(&self.page[start - base..end - base]);
+            }
+            Some((lo, hi)) => {
+                self.store.read_exact(address, dst)?;
+                // Overlay the part that's only in the cache
+                let from = start.max(base + lo);
+                let to = end.min(base + hi);
+                if from < to {
+                    dst[from - start..to - start]
+                        .copy_from_slice(&self.page[from - base..to - base]);
+                }
+            }
+            None => {
+                self.store.read_exact(address, dst)?;
+            }
+        }
+        Ok(dst.len())
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        let mut pos = address as usize;
+        let mut rem = data;
+        while !rem.is_empty() {
+            let base = pos - pos % PAGE;
+            let off = pos - base;
+            let len = rem.len().min(PAGE - off);
+            let (new, rest) = rem.split_at(len);
+            if self.base as usize != base {
+                self.write_back()?;
+                self.base = base as u32;
+            }
+            let dirty = match self.dirty {
+                None => (off, off + len),
+                Some((lo, hi)) => {
+                    // Fill any gap between the cached range and the new data from the store
+                    if off > hi {
+                        self.store
+                            .read_exact(self.base + hi as u32, &mut self.page[hi..off])?;
+                    }
+                    if off + len < lo {
+                        self.store.read_exact(
+                            self.base + (off + len) as u32,
+                            &mut self.page[off + len..lo],
+                        )?;
+                    }
+                    (lo.min(off), hi.max(off + len))
+                }
+            };
+            self.page[off..off + len].copy_from_slice(new);
+            self.dirty = Some(dirty);
+            pos += len;
+            rem = rest;
+        }
+        Ok(data.len())
+    }
+
+    fn capacity(&self) -> u32 {
+        self.store.capacity()
+    }
+
+    /// Write any cached data to the store, and flush it
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.write_back()?;
+        self.store.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Kv, SliceBackedStore, StaticDataStore},
+        std::{collections::hash_map::DefaultHasher, vec::Vec},
+    };
+
+    /// Records the address and length of every write
+    #[derive(Default)]
+    struct Recorder {
+        store: StaticDataStore<256>,
+        writes: Vec<(u32, usize)>,
+        /// Read at most 3 bytes at a time
+        short_reads: bool,
+    }
+
+    impl KvDataAccess for Recorder {
+        type Error = super::super::DataStoreError;
+
+        fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+            let len = match self.short_reads {
+                true => dst.len().min(3),
+                false => dst.len(),
+            };
+            self.store.read(address, &mut dst[..len])
+        }
+
+        fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+            self.writes.push((address, data.len()));
+            self.store.write(address, data)
+        }
+    }
+
+    #[test]
+    fn coalesce() {
+        let mut store = Buffered::<_, 16>::new(Recorder::default());
+        for i in 0..16u8 {
+            assert_eq!(store.write(16 + i as u32, &[i]).unwrap(), 1);
+        }
+        assert_eq!(store.inner().writes.len(), 0);
+
+        // Reads see unflushed writes
+        let mut dst = [0u8; 4];
+        assert_eq!(store.read(14, &mut dst).unwrap(), 4);
+        assert_eq!(dst, [0, 0, 0, 1]);
+        assert_eq!(store.read(30, &mut dst[..2]).unwrap(), 2);
+        assert_eq!(dst[..2], [14, 15]);
+
+        // Writing another page flushes the whole page at once
+        assert_eq!(store.write(30, &[1, 2, 3, 4]).unwrap(), 4);
+        assert_eq!(store.inner().writes, [(16, 16)]);
+
+        // Gaps are filled from the store
+        assert_eq!(store.write(40, &[9]).unwrap(), 1);
+        assert_eq!(store.write(34, &[8]).unwrap(), 1);
+        assert!(store.flush().is_ok());
+        assert_eq!(store.inner().writes, [(16, 16), (32, 9)]);
+        let mut dst = [0u8; 12];
+        assert_eq!(store.read(30, &mut dst).unwrap(), 12);
+        assert_eq!(dst, [1, 2, 3, 4, 8, 0, 0, 0, 0, 0, 9, 0]);
+
+        assert!(store.flush().is_ok());
+        assert_eq!(store.inner().writes.len(), 2);
+    }
+
+    #[test]
+    fn short_reads() {
+        let mut recorder = Recorder::default();
+        assert!(recorder.write_exact(0, &[1, 2, 3, 4, 5, 6, 7, 8]).is_ok());
+        recorder.short_reads = true;
+        let mut store = Buffered::<_, 16>::new(recorder);
+
+        let mut dst = [0u8; 8];
+        assert_eq!(store.read(0, &mut dst).unwrap(), 8);
+        assert_eq!(dst, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // The gap between the writes is filled with what's in the store
+        assert_eq!(store.write(0, &[9]).unwrap(), 1);
+        assert_eq!(store.write(7, &[9]).unwrap(), 1);
+        assert_eq!(store.read(0, &mut dst).unwrap(), 8);
+        assert_eq!(dst, [9, 2, 3, 4, 5, 6, 7, 9]);
+        assert!(store.flush().is_ok());
+        assert_eq!(store.inner().writes.last(), Some(&(0, 8)));
+        assert_eq!(
+            store.inner().store.as_bytes()[..8],
+            [9, 2, 3, 4, 5, 6, 7, 9]
+        );
+    }
+
+    #[test]
+    fn kv() {
+        type BufferedKv = Kv<&'static str, DefaultHasher, Buffered<Recorder, 32>>;
+        let mut kv = BufferedKv::format(Buffered::new(Recorder::default())).unwrap();
+        assert!(kv.insert("a", 42i32).is_ok());
+        assert!(kv.insert("b", 7u64).is_ok());
+        assert!(kv.forget("a").is_ok());
+        assert!(kv.insert("a", 1u8).is_ok());
+        assert!(kv.update("a", 2u8).is_ok());
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(2));
+        assert_eq!(kv.get::<u64>("b").unwrap(), Some(7));
+        assert!(kv.store().flush().is_ok());
+
+        // Every write stays within a page
+        let writes = &kv.store().inner().writes;
+        assert_eq!(
+            writes
+                .iter()
+                .all(|(a, l)| a / 32 == (a + *l as u32 - 1) / 32),
+            true
+        );
+
+        let store = core::mem::replace(kv.store(), Buffered::new(Recorder::default()));
+        let mut kv = BufferedKv::open(Buffered::new(store.into_inner().unwrap())).unwrap();
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(2));
+        assert_eq!(kv.get::<u64>("b").unwrap(), Some(7));
+    }
+}
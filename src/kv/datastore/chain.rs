@@ -0,0 +1,191 @@
+use super::super::KvDataAccess;
+
+#[derive(Debug)]
+pub enum ChainError<A, B> {
+    First(A),
+    Second(B),
+}
+
+impl<A: core::fmt::Display, B: core::fmt::Display> core::fmt::Display for ChainError<A, B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::First(e) => write!(f, "first store: {}", e),
+            Self::Second(e) => write!(f, "second store: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A: std::error::Error + 'static, B: std::error::Error + 'static> std::error::Error
+    for ChainError<A, B>
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::First(e) => Some(e),
+            Self::Second(e) => Some(e),
+        }
+    }
+}
+
+/// Two datastores after each other. Addresses below `first.capacity()` go to `first`, the rest go
+/// to `second`.
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: KvDataAccess, B: KvDataAccess> Chain<A, B> {
+    pub const fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+
+    /// Split an access at the boundary into the lengths for the first and second store
+    fn split(&self, address: u32, len: usize) -> usize {
+        (self.first.capacity() as usize)
+            .saturating_sub(address as usize)
+            .min(len)
+    }
+}
+
+impl<A: KvDataAccess, B: KvDataAccess> KvDataAccess for Chain<A, B> {
+    type Error = ChainError<A::Error, B::Error>;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        let boundary = self.first.capacity();
+        let (a, b) = dst.split_at_mut(self.split(address, dst.len()));
+        let mut read = 0;
+        if !a.is_empty() {
+            read += self.first.read(address, a).map_err(ChainError::First)?;
+            if read < a.len() {
+                return Ok(read);
+            }
+        }
+        if !b.is_empty() {
+            let address = (address + read as u32) - boundary;
+            read += self.second.read(address, b).map_err(ChainError::Second)?;
+        }
+        Ok(read)
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        let boundary = self.first.capacity();
+        let (a, b) = data.split_at(self.split(address, data.len()));
+        let mut written = 0;
+        if !a.is_empty() {
+            written += self.first.write(address, a).map_err(ChainError::First)?;
+            if written < a.len() {
+                return Ok(written);
+            }
+        }
+        if !b.is_empty() {
+            let address = (address + written as u32) - boundary;
+            written += self.second.write(address, b).map_err(ChainError::Second)?;
+        }
+        Ok(written)
+    }
+
+    fn capacity(&self) -> u32 {
+        self.first.capacity().saturating_add(self.second.capacity())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.first.flush().map_err(ChainError::First)?;
+        self.second.flush().map_err(ChainError::Second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{DataStoreError, Kv, KvError, SliceBackedStore, StaticDataStore},
+        std::collections::hash_map::DefaultHasher,
+    };
+
+    #[test]
+    fn straddle() {
+        let mut store = Chain::new(StaticDataStore::<8>::new(), StaticDataStore::<8>::new());
+        assert_eq!(store.capacity(), 16);
+        assert_eq!(store.write(6, &[1, 2, 3, 4]).unwrap(), 4);
+        assert_eq!(store.write(12, &[5]).unwrap(), 1);
+        let mut dst = [0u8; 8];
+        assert_eq!(store.read(5, &mut dst).unwrap(), 8);
+        assert_eq!(dst, [0, 1, 2, 3, 4, 0, 0, 5]);
+
+        let (first, second) = store.clone().into_inner();
+        assert_eq!(first.as_bytes()[6..], [1, 2]);
+        assert_eq!(second.as_bytes()[..5], [3, 4, 0, 0, 5]);
+
+        assert!(matches!(
+            store.write(14, &[0; 4]),
+            Err(ChainError::Second(DataStoreError::OutOfMemory))
+        ));
+    }
+
+    /// Reads and writes at most 3 bytes at a time
+    #[derive(Default)]
+    struct ShortStore(StaticDataStore<8>);
+
+    impl KvDataAccess for ShortStore {
+        type Error = DataStoreError;
+
+        fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+            let len = dst.len().min(3);
+            self.0.read(address, &mut dst[..len])
+        }
+
+        fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+            self.0.write(address, &data[..data.len().min(3)])
+        }
+
+        fn capacity(&self) -> u32 {
+            self.0.capacity()
+        }
+    }
+
+    #[test]
+    fn short_first() {
+        let mut store = Chain::new(ShortStore::default(), StaticDataStore::<8>::new());
+        assert_eq!(store.write(4, &[1, 2, 3, 4, 5, 6]).unwrap(), 3);
+        assert!(store.write_exact(7, &[4, 5, 6]).is_ok());
+        let mut dst = [0u8; 6];
+        assert_eq!(store.read(4, &mut dst).unwrap(), 3);
+        assert!(store.read_exact(4, &mut dst).is_ok());
+        assert_eq!(dst, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(store.first.0.as_bytes()[4..], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn display_error() {
+        use std::string::ToString;
+        let e = ChainError::<DataStoreError, DataStoreError>::Second(DataStoreError::OutOfMemory);
+        assert_eq!(e.to_string(), "second store: out of memory");
+        assert_eq!(
+            KvError::Store(e).to_string(),
+            "store error: second store: out of memory"
+        );
+    }
+
+    #[test]
+    fn kv() {
+        type ChainKv =
+            Kv<&'static str, DefaultHasher, Chain<StaticDataStore<40>, StaticDataStore<96>>>;
+        let mut kv =
+            ChainKv::format(Chain::new(StaticDataStore::new(), StaticDataStore::new())).unwrap();
+        assert!(kv.insert("a", [1u8; 8]).is_ok());
+        assert!(kv.insert("b", [2u8; 8]).is_ok());
+        assert!(kv.insert("c", [3u8; 8]).is_ok());
+        assert!(matches!(
+            kv.insert("d", [4u8; 64]),
+            Err(KvError::Store(ChainError::Second(_)))
+        ));
+        assert_eq!(kv.get::<[u8; 8]>("a").unwrap(), Some([1; 8]));
+        assert_eq!(kv.get::<[u8; 8]>("b").unwrap(), Some([2; 8]));
+        assert_eq!(kv.get::<[u8; 8]>("c").unwrap(), Some([3; 8]));
+    }
+}
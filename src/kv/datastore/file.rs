@@ -0,0 +1,129 @@
+use {
+    super::{super::KvDataAccess, DataStoreError},
+    std::{
+        fs::{File, OpenOptions},
+        io::{Read, Seek, SeekFrom, Write},
+        path::Path,
+    },
+};
+
+#[deprecated(note = "use DataStoreError")]
+pub type FileDataStoreError = DataStoreError;
+
+/// A datastore backed by a file. The store is limited to the length of the file when it was opened.
+#[derive(Debug)]
+pub struct FileDataStore {
+    file: File,
+    len: u32,
+}
+
+impl FileDataStore {
+    /// Open an existing file for reading and writing
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DataStoreError> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let len = u32::try_from(file.metadata()?.len()).unwrap_or(u32::MAX);
+        Ok(Self { file, len })
+    }
+
+    /// Create a zeroed file of `len` bytes, truncating it if it already exists
+    pub fn create(path: impl AsRef<Path>, len: u32) -> Result<Self, DataStoreError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(len as u64)?;
+        Ok(Self { file, len })
+    }
+
+    fn seek(&self, address: u32, len: usize) -> Result<(), DataStoreError> {
+        if address as usize + len > self.len as usize {
+            return Err(DataStoreError::OutOfMemory);
+        }
+        (&self.file).seek(SeekFrom::Start(address as u64))?;
+        Ok(())
+    }
+}
+
+impl KvDataAccess for FileDataStore {
+    type Error = DataStoreError;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        self.seek(address, dst.len())?;
+        (&self.file).read_exact(dst)?;
+        Ok(dst.len())
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        self.seek(address, data.len())?;
+        self.file.write_all(data)?;
+        Ok(data.len())
+    }
+
+    fn capacity(&self) -> u32 {
+        self.len
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(self.file.sync_data()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Kv, KvError},
+        std::collections::hash_map::DefaultHasher,
+    };
+
+    type FileKv = Kv<&'static str, DefaultHasher, FileDataStore>;
+
+    #[test]
+    fn file() {
+        let path = std::env::temp_dir().join(format!("hds-file-{}", std::process::id()));
+        let mut kv = FileKv::format(FileDataStore::create(&path, 256).unwrap()).unwrap();
+
+        // Double insert
+        assert!(kv.insert("a", 42i32).is_ok());
+        assert!(kv.insert("a", 127i32).is_err());
+        assert_eq!(kv.get::<i32>("a").unwrap(), Some(42));
+
+        // Double forget
+        assert!(kv.forget("a").is_ok());
+        assert!(kv.forget("a").is_err());
+        assert_eq!(kv.exists("a").unwrap(), false);
+
+        // Update
+        assert!(kv.insert("a", 1u8).is_ok());
+        assert!(kv.update("a", 2u8).is_ok());
+        assert!(kv.update("a", 3u16).is_err());
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(2));
+        assert!(kv.get::<i32>("a").is_err());
+        assert!(kv.insert("b", 7u64).is_ok());
+
+        // Past the end of the file
+        assert!(matches!(
+            kv.insert_bytes("c", &[0; 256]),
+            Err(KvError::Store(DataStoreError::OutOfMemory))
+        ));
+        drop(kv);
+
+        // Reopen
+        let mut kv = FileKv::open(FileDataStore::open(&path).unwrap()).unwrap();
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(2));
+        assert_eq!(kv.get::<u64>("b").unwrap(), Some(7));
+        assert_eq!(
+            kv.free_space().unwrap(),
+            256 - kv.used_space().unwrap() - crate::kv::HEADER_SZ
+        );
+
+        // Reset
+        assert!(kv.reset().is_ok());
+        assert_eq!(kv.get::<u8>("a").unwrap(), None);
+        drop(kv);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,8 +1,38 @@
-use super::super::KvDataAccess;
+use super::super::{KvDataAccess, SliceBackedStore};
+
+/// How a `HeapDataStore` grows when written past its end
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrowthStrategy {
+    /// Double the size, or grow to fit the write if that's not enough
+    #[default]
+    Doubling,
+    /// Grow by a multiple of the step
+    Fixed(usize),
+    /// Like `Doubling`, but never beyond the given size
+    CapAt(usize),
+}
+
+impl GrowthStrategy {
+    /// New size of a store of `len` bytes that needs to fit `required` bytes
+    fn grow(self, len: usize, required: usize) -> Option<usize> {
+        match self {
+            Self::Doubling => Some((len * 2).max(required)),
+            Self::Fixed(step) => {
+                let step = step.max(1);
+                Some(len + (required - len).div_ceil(step) * step)
+            }
+            Self::CapAt(max) => match required > max {
+                true => None,
+                false => Some((len * 2).clamp(required, max)),
+            },
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct HeapDataStore {
     store: alloc::vec::Vec<u8>,
+    strategy: GrowthStrategy,
 }
 
 impl HeapDataStore {
@@ -11,8 +41,50 @@ impl HeapDataStore {
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_strategy(capacity, GrowthStrategy::Doubling)
+    }
+
+    pub fn with_strategy(capacity: usize, strategy: GrowthStrategy) -> Self {
         Self {
             store: (0..capacity).map(|_| 0u8).collect::<alloc::vec::Vec<_>>(),
+            strategy,
+        }
+    }
+
+    pub fn from_vec(store: alloc::vec::Vec<u8>) -> Self {
+        Self {
+            store,
+            strategy: GrowthStrategy::Doubling,
+        }
+    }
+
+    pub fn into_inner(self) -> alloc::vec::Vec<u8> {
+        self.store
+    }
+
+    /// Truncate the store to `len` bytes and release the memory. Never grows the store.
+    pub fn shrink_to(&mut self, len: usize) {
+        self.store.truncate(len);
+        self.store.shrink_to_fit();
+    }
+
+    /// Grow the store according to the strategy so it fits `required` bytes
+    fn grow_to(&mut self, required: usize) -> Result<(), super::DataStoreError> {
+        if required > self.store.len() {
+            let len = self
+                .strategy
+                .grow(self.store.len(), required)
+                .ok_or(super::DataStoreError::OutOfMemory)?;
+            self.store.resize(len, 0);
+        }
+        Ok(())
+    }
+
+    /// Size to shrink to that fits `len` bytes, rounded up to the growth step
+    pub(in crate::kv) fn shrink_len(&self, len: usize) -> usize {
+        match self.strategy {
+            GrowthStrategy::Fixed(step) if step > 0 => len.next_multiple_of(step),
+            _ => len,
         }
     }
 }
@@ -37,20 +109,87 @@ impl core::ops::DerefMut for HeapDataStore {
 }
 
 impl KvDataAccess for HeapDataStore {
-    type Error = super::SliceDataStoreError;
+    type Error = super::DataStoreError;
 
     fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
         self.store.read(address, dst)
     }
 
     fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
-        let store_size = self.store.len();
-        match self.store.write(address, data) {
-            Err(<Self as KvDataAccess>::Error::OutOfMemory) => {
-                self.extend((0..store_size).map(|_| 0));
-                self.write(address, data)
-            }
-            Ok(l) => Ok(l),
+        self.grow_to(address as usize + data.len())?;
+        self.store.write(address, data)
+    }
+
+    fn reserve(&mut self, len: u32) -> Result<(), Self::Error> {
+        self.grow_to(len as usize)
+    }
+
+    fn capacity(&self) -> u32 {
+        match self.strategy {
+            GrowthStrategy::CapAt(max) => u32::try_from(max).unwrap_or(u32::MAX),
+            _ => u32::MAX,
         }
     }
 }
+
+impl SliceBackedStore for HeapDataStore {
+    fn as_bytes(&self) -> &[u8] {
+        &self.store
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::DataStoreError};
+
+    #[test]
+    fn growth_strategy() {
+        let mut store = HeapDataStore::with_capacity(16);
+        assert!(store.write(16, &[1]).is_ok());
+        assert_eq!(store.len(), 32);
+        assert!(store.write(100, &[1]).is_ok());
+        assert_eq!(store.len(), 101);
+
+        let mut store = HeapDataStore::with_capacity(0);
+        assert!(store.write(0, &[1]).is_ok());
+        assert_eq!(store.len(), 1);
+
+        let mut store = HeapDataStore::with_strategy(16, GrowthStrategy::Fixed(10));
+        assert!(store.write(16, &[1]).is_ok());
+        assert_eq!(store.len(), 26);
+        assert!(store.write(40, &[1, 2]).is_ok());
+        assert_eq!(store.len(), 46);
+
+        let mut store = HeapDataStore::with_strategy(16, GrowthStrategy::CapAt(40));
+        assert_eq!(store.capacity(), 40);
+        assert!(store.write(16, &[1]).is_ok());
+        assert_eq!(store.len(), 32);
+        assert!(store.write(32, &[1]).is_ok());
+        assert_eq!(store.len(), 40);
+        assert!(matches!(
+            store.write(39, &[1, 2]),
+            Err(DataStoreError::OutOfMemory)
+        ));
+        assert_eq!(store.len(), 40);
+    }
+
+    #[test]
+    fn reserve() {
+        let mut store = HeapDataStore::with_capacity(16);
+        assert!(store.reserve(8).is_ok());
+        assert_eq!(store.len(), 16);
+        assert!(store.reserve(100).is_ok());
+        assert_eq!(store.len(), 100);
+
+        let mut store = HeapDataStore::with_strategy(16, GrowthStrategy::CapAt(40));
+        assert!(matches!(
+            store.reserve(41),
+            Err(DataStoreError::OutOfMemory)
+        ));
+        assert_eq!(store.len(), 16);
+    }
+}
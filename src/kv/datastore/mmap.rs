@@ -0,0 +1,102 @@
+use {
+    super::{
+        super::{KvDataAccess, SliceBackedStore},
+        DataStoreError,
+    },
+    memmap2::MmapMut,
+    std::{fs::OpenOptions, path::Path},
+};
+
+/// A datastore backed by a memory mapped file. The store is limited to the length of the file.
+#[derive(Debug)]
+pub struct MmapDataStore {
+    map: MmapMut,
+}
+
+impl MmapDataStore {
+    /// Map an existing file for reading and writing
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated by anyone else, including other processes,
+    /// while it's mapped.
+    pub unsafe fn open(path: impl AsRef<Path>) -> Result<Self, DataStoreError> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        // SAFETY: Upheld by the caller
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Self { map })
+    }
+}
+
+impl KvDataAccess for MmapDataStore {
+    type Error = DataStoreError;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        self.map[..].read(address, dst)
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        self.map[..].write(address, data)
+    }
+
+    fn capacity(&self) -> u32 {
+        self.map[..].capacity()
+    }
+
+    /// Write any modified pages back to the file
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(self.map.flush()?)
+    }
+}
+
+impl SliceBackedStore for MmapDataStore {
+    fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{DataStoreError, Kv, KvError},
+        std::collections::hash_map::DefaultHasher,
+    };
+
+    type MmapKv = Kv<&'static str, DefaultHasher, MmapDataStore>;
+
+    #[test]
+    fn mmap() {
+        let path = std::env::temp_dir().join(format!("hds-mmap-{}", std::process::id()));
+        std::fs::File::create(&path).unwrap().set_len(256).unwrap();
+
+        // SAFETY: The file is private to this test
+        let mut kv = MmapKv::format(unsafe { MmapDataStore::open(&path) }.unwrap()).unwrap();
+        assert!(kv.insert("a", 42i32).is_ok());
+        assert!(kv.insert("b", 7u64).is_ok());
+        assert!(kv.forget("a").is_ok());
+        assert!(kv.insert("a", 2u8).is_ok());
+        assert!(matches!(
+            kv.insert_bytes("c", &[0; 256]),
+            Err(KvError::Store(DataStoreError::OutOfMemory))
+        ));
+        assert!(kv.store().flush().is_ok());
+        drop(kv);
+
+        // SAFETY: See above
+        let mut kv = MmapKv::open(unsafe { MmapDataStore::open(&path) }.unwrap()).unwrap();
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(2));
+        assert_eq!(kv.get::<u64>("b").unwrap(), Some(7));
+        drop(kv);
+
+        std::fs::remove_file(&path).unwrap();
+        // SAFETY: See above
+        assert!(matches!(
+            unsafe { MmapDataStore::open(&path) },
+            Err(DataStoreError::Io(_))
+        ));
+    }
+}
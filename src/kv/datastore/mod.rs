@@ -1,20 +1,117 @@
+mod buffered;
+mod chain;
+#[cfg(feature = "std")]
+mod file;
 #[cfg(feature = "alloc")]
 mod heap;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "embedded-storage")]
+mod nor_flash;
+mod read_only;
 mod r#static;
+mod sub_store;
+mod traced;
+mod xor;
 
+pub use buffered::Buffered;
+pub use chain::{Chain, ChainError};
+#[cfg(feature = "std")]
+#[allow(deprecated)]
+pub use file::{FileDataStore, FileDataStoreError};
 #[cfg(feature = "alloc")]
-pub use heap::HeapDataStore;
+pub use heap::{GrowthStrategy, HeapDataStore};
+#[cfg(feature = "mmap")]
+pub use mmap::MmapDataStore;
+#[cfg(feature = "embedded-storage")]
+pub use nor_flash::NorFlashStore;
+pub use read_only::{ReadOnly, ReadOnlyError};
 
 pub use r#static::StaticDataStore;
+pub use sub_store::{SubStore, SubStoreError};
+pub use traced::{Access, AccessKind, Traced};
+pub use xor::Xor;
 
+/// Error returned by the built-in datastores
 #[derive(Debug)]
 #[non_exhaustive]
-pub enum SliceDataStoreError {
+pub enum DataStoreError {
     OutOfMemory,
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+#[deprecated(note = "use DataStoreError")]
+pub type SliceDataStoreError = DataStoreError;
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for DataStoreError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl core::fmt::Display for DataStoreError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::OutOfMemory => f.write_str("out of memory"),
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DataStoreError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::OutOfMemory => defmt::write!(f, "OutOfMemory"),
+            #[cfg(feature = "std")]
+            Self::Io(e) => defmt::write!(f, "Io({})", defmt::Display2Format(e)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DataStoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<S: super::KvDataAccess + ?Sized> super::KvDataAccess for &mut S {
+    type Error = S::Error;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        (**self).read(address, dst)
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        (**self).write(address, data)
+    }
+
+    fn capacity(&self) -> u32 {
+        (**self).capacity()
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        (**self).flush()
+    }
+
+    fn reserve(&mut self, len: u32) -> Result<(), Self::Error> {
+        (**self).reserve(len)
+    }
+
+    fn erase(&mut self, address: u32, len: u32) -> Result<(), Self::Error> {
+        (**self).erase(address, len)
+    }
 }
 
 impl super::KvDataAccess for [u8] {
-    type Error = SliceDataStoreError;
+    type Error = DataStoreError;
 
     fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
         let addr = address as usize;
@@ -35,4 +132,84 @@ impl super::KvDataAccess for [u8] {
         self[addr..end].copy_from_slice(data);
         Ok(data.len())
     }
+
+    fn capacity(&self) -> u32 {
+        u32::try_from(self.len()).unwrap_or(u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::KvDataAccess};
+
+    #[test]
+    fn tail_access() {
+        let mut store = StaticDataStore::<8>::new();
+        assert_eq!(store.write(4, &[1, 2, 3, 4]).unwrap(), 4);
+        let mut dst = [0u8; 4];
+        assert_eq!(store.read(4, &mut dst).unwrap(), 4);
+        assert_eq!(dst, [1, 2, 3, 4]);
+        assert!(matches!(
+            store.write(5, &[1, 2, 3, 4]),
+            Err(DataStoreError::OutOfMemory)
+        ));
+        assert!(matches!(
+            store.read(5, &mut dst),
+            Err(DataStoreError::OutOfMemory)
+        ));
+
+        let slice = &mut [0u8; 4][..];
+        assert_eq!(slice.write(3, &[9]).unwrap(), 1);
+        let mut dst = [0u8; 1];
+        assert_eq!(slice.read(3, &mut dst).unwrap(), 1);
+        assert_eq!(dst, [9]);
+    }
+
+    #[test]
+    fn unified_error() {
+        let mut dst = [0u8; 4];
+        assert!(matches!(
+            StaticDataStore::<2>::new().read(0, &mut dst),
+            Err(DataStoreError::OutOfMemory)
+        ));
+        assert!(matches!(
+            [0u8; 2][..].write(0, &dst),
+            Err(DataStoreError::OutOfMemory)
+        ));
+        #[cfg(feature = "alloc")]
+        assert!(matches!(
+            HeapDataStore::with_strategy(2, GrowthStrategy::CapAt(2)).write(0, &dst),
+            Err(DataStoreError::OutOfMemory)
+        ));
+        #[cfg(feature = "std")]
+        {
+            let path = std::env::temp_dir().join(format!("hds-error-{}", std::process::id()));
+            let store = FileDataStore::create(&path, 2).unwrap();
+            assert!(matches!(
+                store.read(0, &mut dst),
+                Err(DataStoreError::OutOfMemory)
+            ));
+            std::fs::remove_file(&path).unwrap();
+            assert!(matches!(
+                FileDataStore::open(&path),
+                Err(DataStoreError::Io(_))
+            ));
+        }
+
+        // The old name still works
+        #[allow(deprecated)]
+        {
+            let e: SliceDataStoreError = DataStoreError::OutOfMemory;
+            assert!(matches!(e, SliceDataStoreError::OutOfMemory));
+        }
+    }
+
+    #[test]
+    fn capacity() {
+        assert_eq!(StaticDataStore::<8>::new().capacity(), 8);
+        assert_eq!(StaticDataStore::<0>::new().capacity(), 0);
+        assert_eq!([0u8; 5][..].capacity(), 5);
+        #[cfg(feature = "alloc")]
+        assert_eq!(HeapDataStore::new().capacity(), u32::MAX);
+    }
 }
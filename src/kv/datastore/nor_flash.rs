@@ -0,0 +1,229 @@
+use {super::super::KvDataAccess, core::cell::RefCell, embedded_storage::nor_flash::NorFlash};
+
+/// A datastore on NOR flash. `ERASE_SIZE` must match `F::ERASE_SIZE`, which is checked at compile
+/// time, and is the size of the buffer used to read-modify-erase-write a sector.
+///
+/// Writes to erased flash are programmed directly, anything else erases and rewrites the whole sector.
+/// Erased flash doesn't contain a valid `Kv`, use `Kv::format` to create one.
+#[derive(Debug)]
+pub struct NorFlashStore<F, const ERASE_SIZE: usize> {
+    flash: RefCell<F>,
+    buf: RefCell<[u8; ERASE_SIZE]>,
+}
+
+impl<F: NorFlash, const ERASE_SIZE: usize> NorFlashStore<F, ERASE_SIZE> {
+    pub fn new(flash: F) -> Self {
+        const {
+            assert!(
+                ERASE_SIZE == F::ERASE_SIZE,
+                "ERASE_SIZE must match F::ERASE_SIZE"
+            )
+        };
+        Self {
+            flash: RefCell::new(flash),
+            buf: RefCell::new([0; ERASE_SIZE]),
+        }
+    }
+
+    pub fn flash(&mut self) -> &mut F {
+        self.flash.get_mut()
+    }
+
+    pub fn into_inner(self) -> F {
+        self.flash.into_inner()
+    }
+}
+
+impl<F: NorFlash, const ERASE_SIZE: usize> KvDataAccess for NorFlashStore<F, ERASE_SIZE> {
+    type Error = F::Error;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        let mut flash = self.flash.borrow_mut();
+        let mut buf = self.buf.borrow_mut();
+        let mut pos = address as usize;
+        let mut done = 0;
+        while done < dst.len() {
+            let sector = pos - pos % ERASE_SIZE;
+            let off = pos - sector;
+            let len = (dst.len() - done).min(ERASE_SIZE - off);
+            // Reads have to be aligned to F::READ_SIZE
+            let start = off - off % F::READ_SIZE;
+            let end = (off + len).next_multiple_of(F::READ_SIZE);
+            flash.read((sector + start) as u32, &mut buf[start..end])?;
+            dst[done..done + len].copy_from_slice(&buf[off..off + len]);
+            pos += len;
+            done += len;
+        }
+        Ok(dst.len())
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        let flash = self.flash.get_mut();
+        let buf = self.buf.get_mut();
+        let mut pos = address as usize;
+        let mut rem = data;
+        while !rem.is_empty() {
+            let sector = pos - pos % ERASE_SIZE;
+            let off = pos - sector;
+            let len = rem.len().min(ERASE_SIZE - off);
+            let (new, rest) = rem.split_at(len);
+            flash.read(sector as u32, buf)?;
+            if buf[off..off + len] != *new {
+                // Writes have to be aligned to F::WRITE_SIZE
+                let start = off - off % F::WRITE_SIZE;
+                let end = (off + len).next_multiple_of(F::WRITE_SIZE);
+                let erased = buf[start..end].iter().all(|b| *b == 0xff);
+                buf[off..off + len].copy_from_slice(new);
+                match erased {
+                    true => flash.write((sector + start) as u32, &buf[start..end])?,
+                    false => {
+                        flash.erase(sector as u32, (sector + ERASE_SIZE) as u32)?;
+                        flash.write(sector as u32, buf)?;
+                    }
+                }
+            }
+            pos += len;
+            rem = rest;
+        }
+        Ok(data.len())
+    }
+
+    fn capacity(&self) -> u32 {
+        u32::try_from(self.flash.borrow().capacity()).unwrap_or(u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Kv, KvError},
+        embedded_storage::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash},
+        std::collections::hash_map::DefaultHasher,
+    };
+
+    /// 4 sectors of 256 bytes
+    struct MockFlash {
+        mem: [u8; 1024],
+        erases: usize,
+    }
+
+    impl MockFlash {
+        fn new() -> Self {
+            Self {
+                mem: [0xff; 1024],
+                erases: 0,
+            }
+        }
+    }
+
+    impl ErrorType for MockFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for MockFlash {
+        const READ_SIZE: usize = 4;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            embedded_storage::nor_flash::check_read(self, offset, bytes.len())?;
+            bytes.copy_from_slice(&self.mem[offset as usize..][..bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.mem.len()
+        }
+    }
+
+    impl NorFlash for MockFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 256;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            embedded_storage::nor_flash::check_erase(self, from, to)?;
+            self.mem[from as usize..to as usize].fill(0xff);
+            self.erases += 1;
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            embedded_storage::nor_flash::check_write(self, offset, bytes.len())?;
+            let dst = &mut self.mem[offset as usize..][..bytes.len()];
+            // Programming can only clear bits
+            assert_eq!(dst.iter().all(|b| *b == 0xff), true);
+            dst.copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn erase_when_needed() {
+        let mut store = NorFlashStore::<_, 256>::new(MockFlash::new());
+        assert_eq!(store.capacity(), 1024);
+
+        // Erased flash is programmed directly, also across sectors
+        assert_eq!(store.write(254, &[1, 2, 3, 4]).unwrap(), 4);
+        assert_eq!(store.write(1, &[5]).unwrap(), 1);
+        assert_eq!(store.flash().erases, 0);
+
+        // Same data, nothing to do
+        assert_eq!(store.write(254, &[1, 2, 3, 4]).unwrap(), 4);
+        assert_eq!(store.flash().erases, 0);
+
+        // Overwriting erases the sector but keeps the rest of it
+        assert_eq!(store.write(4, &[6]).unwrap(), 1);
+        assert_eq!(store.flash().erases, 0);
+        assert_eq!(store.write(1, &[7]).unwrap(), 1);
+        assert_eq!(store.flash().erases, 1);
+
+        let mut dst = [0u8; 6];
+        assert_eq!(store.read(253, &mut dst).unwrap(), 6);
+        assert_eq!(dst, [0xff, 1, 2, 3, 4, 0xff]);
+        let mut dst = [0u8; 5];
+        assert_eq!(store.read(0, &mut dst).unwrap(), 5);
+        assert_eq!(dst, [0xff, 7, 0xff, 0xff, 6]);
+
+        assert_eq!(
+            store.write(1023, &[0, 0]).unwrap_err(),
+            NorFlashErrorKind::OutOfBounds
+        );
+    }
+
+    #[test]
+    fn kv() {
+        type FlashKv = Kv<&'static str, DefaultHasher, NorFlashStore<MockFlash, 256>>;
+        let mut kv = FlashKv::format(NorFlashStore::new(MockFlash::new())).unwrap();
+        assert_eq!(kv.store().flash().erases, 0);
+
+        // Double insert
+        assert!(kv.insert("a", 42i32).is_ok());
+        assert!(kv.insert("a", 127i32).is_err());
+        assert_eq!(kv.get::<i32>("a").unwrap(), Some(42));
+
+        // Double forget
+        assert!(kv.forget("a").is_ok());
+        assert!(kv.forget("a").is_err());
+        assert_eq!(kv.exists("a").unwrap(), false);
+
+        // Update
+        assert!(kv.insert("a", 1u8).is_ok());
+        assert!(kv.update("a", 2u8).is_ok());
+        assert!(kv.update("a", 3u16).is_err());
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(2));
+        assert!(kv.get::<i32>("a").is_err());
+        assert!(kv.insert("b", 7u64).is_ok());
+
+        // Past the end of the flash
+        assert!(matches!(
+            kv.insert_bytes("c", &[0; 1024]),
+            Err(KvError::Store(NorFlashErrorKind::OutOfBounds))
+        ));
+
+        // Reopen
+        let flash = core::mem::replace(kv.store(), NorFlashStore::new(MockFlash::new()));
+        let mut kv = FlashKv::open(flash).unwrap();
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(2));
+        assert_eq!(kv.get::<u64>("b").unwrap(), Some(7));
+        assert!(kv.store().flash().erases > 0);
+    }
+}
@@ -0,0 +1,122 @@
+use super::super::KvDataAccess;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReadOnlyError<E> {
+    ReadOnly,
+    Store(E),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for ReadOnlyError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ReadOnly => f.write_str("store is read-only"),
+            Self::Store(e) => write!(f, "inner store: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for ReadOnlyError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ReadOnly => None,
+            Self::Store(e) => Some(e),
+        }
+    }
+}
+
+/// A datastore wrapper that forwards reads and refuses all writes
+#[derive(Debug, Clone)]
+pub struct ReadOnly<S> {
+    store: S,
+}
+
+impl<S> ReadOnly<S> {
+    pub const fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+}
+
+impl<S: KvDataAccess> KvDataAccess for ReadOnly<S> {
+    type Error = ReadOnlyError<S::Error>;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        self.store.read(address, dst).map_err(ReadOnlyError::Store)
+    }
+
+    fn write(&mut self, _address: u32, _data: &[u8]) -> Result<usize, Self::Error> {
+        Err(ReadOnlyError::ReadOnly)
+    }
+
+    fn capacity(&self) -> u32 {
+        self.store.capacity()
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.store.flush().map_err(ReadOnlyError::Store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{DataStoreError, Kv, KvError, StaticDataStore},
+        std::collections::hash_map::DefaultHasher,
+    };
+
+    #[test]
+    fn read_only() {
+        let mut kv = Kv::<_, DefaultHasher, _>::format(StaticDataStore::<64>::new()).unwrap();
+        assert!(kv.insert("a", 42u32).is_ok());
+
+        let mut kv = Kv::<_, DefaultHasher, _>::open(ReadOnly::new(kv.store().clone())).unwrap();
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(42));
+        assert_eq!(kv.exists("b").unwrap(), false);
+
+        assert!(matches!(
+            kv.insert("b", 1u8),
+            Err(KvError::Store(ReadOnlyError::ReadOnly))
+        ));
+        assert!(matches!(
+            kv.update("a", 1u32),
+            Err(KvError::Store(ReadOnlyError::ReadOnly))
+        ));
+        assert!(matches!(
+            kv.forget("a"),
+            Err(KvError::Store(ReadOnlyError::ReadOnly))
+        ));
+        assert!(matches!(
+            kv.reset(),
+            Err(KvError::Store(ReadOnlyError::ReadOnly))
+        ));
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(42));
+
+        let mut dst = [0u8; 4];
+        assert!(matches!(
+            kv.store().read(62, &mut dst),
+            Err(ReadOnlyError::Store(_))
+        ));
+        assert!(matches!(
+            kv.store().write(0, &[]),
+            Err(ReadOnlyError::ReadOnly)
+        ));
+    }
+
+    #[test]
+    fn display_error() {
+        use std::string::ToString;
+        let e = ReadOnlyError::<DataStoreError>::ReadOnly;
+        assert_eq!(e.to_string(), "store is read-only");
+        let e = ReadOnlyError::Store(DataStoreError::OutOfMemory);
+        assert_eq!(
+            KvError::Store(e).to_string(),
+            "store error: inner store: out of memory"
+        );
+    }
+}
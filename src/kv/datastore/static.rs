@@ -1,4 +1,4 @@
-use super::super::KvDataAccess;
+use super::super::{KvDataAccess, SliceBackedStore};
 
 #[derive(Debug, Clone)]
 pub struct StaticDataStore<const SIZE: usize> {
@@ -9,10 +9,24 @@ impl<const SIZE: usize> StaticDataStore<SIZE> {
     pub const fn new() -> Self {
         Self { store: [0; SIZE] }
     }
+
+    pub const fn from_array(store: [u8; SIZE]) -> Self {
+        Self { store }
+    }
+
+    pub const fn into_inner(self) -> [u8; SIZE] {
+        self.store
+    }
+}
+
+impl<const SIZE: usize> Default for StaticDataStore<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<const SIZE: usize> KvDataAccess for StaticDataStore<SIZE> {
-    type Error = super::SliceDataStoreError;
+    type Error = super::DataStoreError;
 
     fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
         self.store.as_slice().read(address, dst)
@@ -21,4 +35,18 @@ impl<const SIZE: usize> KvDataAccess for StaticDataStore<SIZE> {
     fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
         self.store.as_mut_slice().write(address, data)
     }
+
+    fn capacity(&self) -> u32 {
+        u32::try_from(SIZE).unwrap_or(u32::MAX)
+    }
+}
+
+impl<const SIZE: usize> SliceBackedStore for StaticDataStore<SIZE> {
+    fn as_bytes(&self) -> &[u8] {
+        &self.store
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.store
+    }
 }
@@ -0,0 +1,162 @@
+use super::super::KvDataAccess;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SubStoreError<E> {
+    OutOfMemory,
+    Store(E),
+}
+
+/// Errors of the wrapped store, e.g. when merging from a `Kv` on the wrapped store type
+impl<E> From<E> for SubStoreError<E> {
+    fn from(e: E) -> Self {
+        Self::Store(e)
+    }
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for SubStoreError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::OutOfMemory => f.write_str("access is outside of the sub store"),
+            Self::Store(e) => write!(f, "inner store: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for SubStoreError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OutOfMemory => None,
+            Self::Store(e) => Some(e),
+        }
+    }
+}
+
+/// A view of `len` bytes of another datastore, starting at `offset`
+#[derive(Debug, Clone)]
+pub struct SubStore<S> {
+    store: S,
+    offset: u32,
+    len: u32,
+}
+
+impl<S> SubStore<S> {
+    pub const fn new(store: S, offset: u32, len: u32) -> Self {
+        Self { store, offset, len }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    fn translate<E>(&self, address: u32, len: usize) -> Result<u32, SubStoreError<E>> {
+        match address as usize + len > self.len as usize {
+            true => Err(SubStoreError::OutOfMemory),
+            false => Ok(self.offset + address),
+        }
+    }
+}
+
+impl<S: KvDataAccess> KvDataAccess for SubStore<S> {
+    type Error = SubStoreError<S::Error>;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        let address = self.translate(address, dst.len())?;
+        self.store.read(address, dst).map_err(SubStoreError::Store)
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        let address = self.translate(address, data.len())?;
+        self.store
+            .write(address, data)
+            .map_err(SubStoreError::Store)
+    }
+
+    fn capacity(&self) -> u32 {
+        self.len
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.store.flush().map_err(SubStoreError::Store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{DataStoreError, Kv, KvError, StaticDataStore},
+        std::collections::hash_map::DefaultHasher,
+    };
+
+    type SubKv<'a> = Kv<&'static str, DefaultHasher, SubStore<&'a mut StaticDataStore<256>>>;
+
+    #[test]
+    fn sub_store() {
+        let mut store = StaticDataStore::<256>::new();
+
+        let mut a = SubKv::format(SubStore::new(&mut store, 0, 128)).unwrap();
+        assert!(a.insert("a", 1u32).is_ok());
+        assert!(a.insert("shared", 1u64).is_ok());
+        assert_eq!(
+            a.free_space().unwrap(),
+            128 - crate::kv::HEADER_SZ - a.used_space().unwrap()
+        );
+        assert!(matches!(
+            a.insert_bytes("big", &[0xff; 128]),
+            Err(KvError::Store(SubStoreError::OutOfMemory))
+        ));
+
+        let mut b = SubKv::format(SubStore::new(&mut store, 128, 128)).unwrap();
+        assert!(b.insert("b", 2u32).is_ok());
+        assert!(b.insert("shared", 2u64).is_ok());
+
+        let mut a = SubKv::open(SubStore::new(&mut store, 0, 128)).unwrap();
+        assert_eq!(a.get::<u32>("a").unwrap(), Some(1));
+        assert_eq!(a.get::<u64>("shared").unwrap(), Some(1));
+        assert_eq!(a.exists("b").unwrap(), false);
+
+        let mut b = SubKv::open(SubStore::new(&mut store, 128, 128)).unwrap();
+        assert_eq!(b.get::<u32>("b").unwrap(), Some(2));
+        assert_eq!(b.get::<u64>("shared").unwrap(), Some(2));
+        assert_eq!(b.exists("a").unwrap(), false);
+    }
+
+    #[test]
+    fn nested() {
+        let mut outer = SubStore::new(SubStore::new(StaticDataStore::<64>::new(), 16, 32), 8, 16);
+        assert_eq!(outer.capacity(), 16);
+        assert_eq!(outer.write(12, &[1, 2, 3, 4]).unwrap(), 4);
+        assert!(matches!(
+            outer.write(13, &[0; 4]),
+            Err(SubStoreError::OutOfMemory)
+        ));
+        let mut dst = [0u8; 4];
+        assert_eq!(
+            outer.into_inner().into_inner().read(36, &mut dst).unwrap(),
+            4
+        );
+        assert_eq!(dst, [1, 2, 3, 4]);
+
+        let inner = SubStore::new(SubStore::new(StaticDataStore::<64>::new(), 16, 32), 8, 32);
+        assert!(matches!(
+            inner.read(28, &mut dst),
+            Err(SubStoreError::Store(SubStoreError::OutOfMemory))
+        ));
+    }
+
+    #[test]
+    fn display_error() {
+        use std::string::ToString;
+        let e = SubStoreError::<SubStoreError<DataStoreError>>::Store(SubStoreError::OutOfMemory);
+        assert_eq!(
+            e.to_string(),
+            "inner store: access is outside of the sub store"
+        );
+        assert_eq!(
+            KvError::Store(e).to_string(),
+            "store error: inner store: access is outside of the sub store"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use {super::super::KvDataAccess, core::cell::RefCell};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A single read or write passed to the callback of `Traced`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Access {
+    pub kind: AccessKind,
+    pub address: u32,
+    pub len: usize,
+    /// Up to the first 8 bytes read or written, zero padded
+    pub head: [u8; 8],
+    /// If the store returned an error. `head` is zeroed for failed reads.
+    pub failed: bool,
+}
+
+impl Access {
+    fn new(kind: AccessKind, address: u32, data: &[u8], failed: bool) -> Self {
+        let mut head = [0u8; 8];
+        let n = data.len().min(head.len());
+        if !failed {
+            head[..n].copy_from_slice(&data[..n]);
+        }
+        Self {
+            kind,
+            address,
+            len: data.len(),
+            head,
+            failed,
+        }
+    }
+}
+
+/// A datastore wrapper that passes every access to a callback, for debugging
+#[derive(Debug)]
+pub struct Traced<S, F> {
+    store: S,
+    trace: RefCell<F>,
+}
+
+impl<S, F: FnMut(Access)> Traced<S, F> {
+    pub const fn new(store: S, trace: F) -> Self {
+        Self {
+            store,
+            trace: RefCell::new(trace),
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+}
+
+impl<S: KvDataAccess, F: FnMut(Access)> KvDataAccess for Traced<S, F> {
+    type Error = S::Error;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        let res = self.store.read(address, dst);
+        (self.trace.borrow_mut())(Access::new(AccessKind::Read, address, dst, res.is_err()));
+        res
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        let res = self.store.write(address, data);
+        (self.trace.get_mut())(Access::new(AccessKind::Write, address, data, res.is_err()));
+        res
+    }
+
+    fn capacity(&self) -> u32 {
+        self.store.capacity()
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.store.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            kv::{AMOUNT_ADDR, HEADER_SZ, JOURNAL_ADDR, SIZE_ADDR, TAG_SZ},
+            Kv, StaticDataStore,
+        },
+        std::{cell::RefCell, collections::hash_map::DefaultHasher, vec::Vec},
+    };
+
+    #[test]
+    fn traced() {
+        let log = RefCell::new(Vec::new());
+        {
+            let store = Traced::new(StaticDataStore::<128>::new(), |a| log.borrow_mut().push(a));
+            let mut kv =
+                Kv::<_, DefaultHasher, _>::with_hasher_and_store(DefaultHasher::new(), store);
+            assert!(kv.insert("a", 0x0102_0304u32).is_ok());
+            assert!(kv.get::<u32>("b").is_ok());
+        }
+
+        use AccessKind::{Read, Write};
+        let log = log.into_inner();
+        let accesses = log
+            .iter()
+            .map(|a| (a.kind, a.address, a.len))
+            .collect::<Vec<_>>();
+
+        // The accesses depend on the layout
+        let meta = Kv::<(), (), ()>::META_SZ;
+        let addr = HEADER_SZ;
+        // Look for the key, then get the address of the new value
+        let mut expected = Vec::from([(Read, AMOUNT_ADDR, 4), (Read, SIZE_ADDR, 4)]);
+        if cfg!(feature = "journal") {
+            // Size, amount, address, length and progress, then the state
+            expected.extend([(Read, SIZE_ADDR, 4), (Read, AMOUNT_ADDR, 4)]);
+            expected.extend((1..6).map(|i| (Write, JOURNAL_ADDR + 4 * i, 4)));
+            expected.push((Write, JOURNAL_ADDR, 4));
+        }
+        // Key and size
+        expected.extend([(Write, addr, 8), (Write, addr + 8, 4)]);
+        if cfg!(feature = "type-tag") {
+            expected.push((Write, addr + 12, 4));
+        }
+        if cfg!(feature = "crc") {
+            expected.push((Write, addr + 12 + TAG_SZ, 4));
+        }
+        // Data, amount and size
+        #[rustfmt::skip]
+        expected.extend([
+            (Write, addr + meta, 4),
+            (Read, AMOUNT_ADDR, 4), (Write, AMOUNT_ADDR, 4), (Read, SIZE_ADDR, 4), (Write, SIZE_ADDR, 4),
+        ]);
+        if cfg!(feature = "journal") {
+            expected.push((Write, JOURNAL_ADDR, 4));
+        }
+        // Look for "b", reading the header of "a"
+        expected.extend([(Read, AMOUNT_ADDR, 4), (Read, addr, 8), (Read, addr + 8, 4)]);
+        assert_eq!(accesses, expected);
+
+        let data = log
+            .iter()
+            .find(|a| a.kind == Write && a.address == addr + meta);
+        assert_eq!(data.unwrap().head, [4, 3, 2, 1, 0, 0, 0, 0]);
+        let size = log
+            .iter()
+            .rfind(|a| a.kind == Write && a.address == SIZE_ADDR);
+        assert_eq!(size.unwrap().head[..4], (meta + 4).to_le_bytes());
+        assert_eq!(log.iter().any(|a| a.failed), false);
+    }
+}
@@ -0,0 +1,139 @@
+use super::super::KvDataAccess;
+
+/// A datastore wrapper that XORs every byte with a keystream derived from its address and `key`.
+///
+/// This is obfuscation, not encryption. It's trivial to recover the key from a store with known
+/// contents, so don't rely on it to protect secrets.
+#[derive(Debug, Clone)]
+pub struct Xor<S> {
+    store: S,
+    key: u64,
+}
+
+impl<S> Xor<S> {
+    pub const fn new(store: S, key: u64) -> Self {
+        Self { store, key }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    fn apply(&self, address: u32, data: &mut [u8]) {
+        for (i, b) in data.iter_mut().enumerate() {
+            *b ^= keystream(self.key, address.wrapping_add(i as u32));
+        }
+    }
+}
+
+/// Mix the key and address (splitmix64 finalizer)
+fn keystream(key: u64, address: u32) -> u8 {
+    let mut x = key ^ (address as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (x ^ (x >> 31)) as u8
+}
+
+impl<S: KvDataAccess> KvDataAccess for Xor<S> {
+    type Error = S::Error;
+
+    fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+        let read = self.store.read(address, dst)?;
+        self.apply(address, &mut dst[..read]);
+        Ok(read)
+    }
+
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+        let mut buf = [0u8; 32];
+        let mut written = 0;
+        for chunk in data.chunks(buf.len()) {
+            let addr = address + written as u32;
+            let buf = &mut buf[..chunk.len()];
+            buf.copy_from_slice(chunk);
+            self.apply(addr, buf);
+            let chunk_written = self.store.write(addr, buf)?;
+            written += chunk_written;
+            if chunk_written < buf.len() {
+                break;
+            }
+        }
+        Ok(written)
+    }
+
+    fn capacity(&self) -> u32 {
+        self.store.capacity()
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.store.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Kv, SliceBackedStore, StaticDataStore},
+        std::collections::hash_map::DefaultHasher,
+    };
+
+    #[test]
+    fn xor() {
+        let mut store = Xor::new(StaticDataStore::<64>::new(), 0x1234_5678);
+        let data = [0xaa; 40];
+        assert_eq!(store.write(3, &data).unwrap(), 40);
+        let mut dst = [0u8; 40];
+        assert_eq!(store.read(3, &mut dst).unwrap(), 40);
+        assert_eq!(dst, data);
+        assert_ne!(&store.clone().into_inner().as_bytes()[3..43], &data);
+
+        // A different key doesn't decode it
+        let other = Xor::new(store.into_inner(), 0x1234_5679);
+        assert_eq!(other.read(3, &mut dst).unwrap(), 40);
+        assert_ne!(dst, data);
+    }
+
+    #[test]
+    fn short_writes() {
+        /// Writes at most 3 bytes at a time
+        #[derive(Default)]
+        struct ShortStore(StaticDataStore<64>);
+
+        impl KvDataAccess for ShortStore {
+            type Error = crate::DataStoreError;
+
+            fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+                self.0.read(address, dst)
+            }
+
+            fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+                self.0.write(address, &data[..data.len().min(3)])
+            }
+        }
+
+        let mut store = Xor::new(ShortStore::default(), 7);
+        let data: [u8; 40] = core::array::from_fn(|i| i as u8);
+        assert_eq!(store.write(5, &data).unwrap(), 3);
+        assert!(store.write_exact(5, &data).is_ok());
+        let mut dst = [0u8; 40];
+        assert!(store.read_exact(5, &mut dst).is_ok());
+        assert_eq!(dst, data);
+    }
+
+    #[test]
+    fn kv() {
+        type XorKv = Kv<&'static str, DefaultHasher, Xor<StaticDataStore<128>>>;
+        let secret = *b"hunter2!";
+        let mut kv = XorKv::format(Xor::new(StaticDataStore::new(), 42)).unwrap();
+        assert!(kv.insert("password", secret).is_ok());
+        assert!(kv.insert("pin", 1234u32).is_ok());
+
+        let raw = kv.store().clone().into_inner();
+        assert_eq!(raw.as_bytes().windows(8).any(|w| w == secret), false);
+        assert_eq!(raw.as_bytes().windows(4).any(|w| w == b"HDKV"), false);
+
+        let mut kv = XorKv::open(Xor::new(raw, 42)).unwrap();
+        assert_eq!(kv.get::<[u8; 8]>("password").unwrap(), Some(secret));
+        assert_eq!(kv.get::<u32>("pin").unwrap(), Some(1234));
+    }
+}
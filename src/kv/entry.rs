@@ -0,0 +1,58 @@
+use {
+    super::{bytes_of, type_tag, ByteOrder, KeyWidth, Kv, KvDataAccess, KvError, LittleEndian},
+    core::hash::{Hash, Hasher},
+};
+
+/// A view into a single key of a [`Kv`], created with [`Kv::entry`].
+pub struct Entry<'a, K, H, S, B = LittleEndian, W = u64> {
+    kv: &'a mut Kv<K, H, S, B, W>,
+    key: u64,
+    addr: Option<u32>,
+}
+
+impl<'a, K: Hash, H: Hasher + Clone, S: KvDataAccess, B: ByteOrder, W: KeyWidth>
+    Entry<'a, K, H, S, B, W>
+{
+    pub(super) fn new(kv: &'a mut Kv<K, H, S, B, W>, key: u64) -> Result<Self, KvError<S::Error>> {
+        let addr = kv.find(key)?;
+        Ok(Self { kv, key, addr })
+    }
+
+    /// Returns the value if present, otherwise inserts `default` and returns it.
+    pub fn or_insert<T: Copy + 'static>(self, default: T) -> Result<T, KvError<S::Error>> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns the value if present, otherwise inserts the result of `f` and returns it.
+    pub fn or_insert_with<T: Copy + 'static, F: FnOnce() -> T>(
+        self,
+        f: F,
+    ) -> Result<T, KvError<S::Error>> {
+        match self.addr {
+            Some(addr) => self.kv.read_value(addr),
+            None => {
+                let v = f();
+                self.kv.append(self.key, type_tag::<T>(), bytes_of(&v))?;
+                Ok(v)
+            }
+        }
+    }
+
+    /// Modify the value in place if present.
+    pub fn and_modify<T: Copy + 'static, F: FnOnce(&mut T)>(
+        self,
+        f: F,
+    ) -> Result<Self, KvError<S::Error>> {
+        if let Some(addr) = self.addr {
+            let mut v = self.kv.read_value::<T>(addr)?;
+            f(&mut v);
+            self.kv.write_value(addr, bytes_of(&v))?;
+        }
+        Ok(self)
+    }
+
+    /// Whether the key is present
+    pub fn is_occupied(&self) -> bool {
+        self.addr.is_some()
+    }
+}
@@ -0,0 +1,105 @@
+use super::ByteOrder;
+
+/// Width of the keys stored in a [`Kv`](super::Kv). Hashes are truncated to this width, so narrower
+/// keys save space but collide sooner.
+pub trait KeyWidth {
+    /// Size of a stored key in bytes
+    const SIZE: u32;
+    /// Key marking a forgotten value, also the mask of the width
+    const TOMBSTONE: u64;
+
+    /// Write the low `SIZE` bytes of `key` to `dst`
+    fn encode<B: ByteOrder>(key: u64, dst: &mut [u8]);
+    fn decode<B: ByteOrder>(src: &[u8]) -> u64;
+
+    /// Truncate a hash to a key, never returning the tombstone
+    fn from_hash(hash: u64) -> u64 {
+        let key = hash & Self::TOMBSTONE;
+        match key == Self::TOMBSTONE {
+            true => key - 1,
+            false => key,
+        }
+    }
+}
+
+impl KeyWidth for u16 {
+    const SIZE: u32 = 2;
+    const TOMBSTONE: u64 = u16::MAX as u64;
+
+    // With the key in both halves of a u32, the first two bytes are the key in either byte order
+    fn encode<B: ByteOrder>(key: u64, dst: &mut [u8]) {
+        let key = key as u32 & 0xffff;
+        dst.copy_from_slice(&B::to_bytes(key | key << 16)[..2]);
+    }
+
+    fn decode<B: ByteOrder>(src: &[u8]) -> u64 {
+        (B::from_bytes([src[0], src[1], src[0], src[1]]) & 0xffff) as u64
+    }
+}
+
+impl KeyWidth for u32 {
+    const SIZE: u32 = 4;
+    const TOMBSTONE: u64 = u32::MAX as u64;
+
+    fn encode<B: ByteOrder>(key: u64, dst: &mut [u8]) {
+        dst.copy_from_slice(&B::to_bytes(key as u32));
+    }
+
+    fn decode<B: ByteOrder>(src: &[u8]) -> u64 {
+        B::from_bytes([src[0], src[1], src[2], src[3]]) as u64
+    }
+}
+
+/// Stored as two 32-bit words, low word first
+impl KeyWidth for u64 {
+    const SIZE: u32 = 8;
+    const TOMBSTONE: u64 = u64::MAX;
+
+    fn encode<B: ByteOrder>(key: u64, dst: &mut [u8]) {
+        u32::encode::<B>(key, &mut dst[..4]);
+        u32::encode::<B>(key >> 32, &mut dst[4..]);
+    }
+
+    fn decode<B: ByteOrder>(src: &[u8]) -> u64 {
+        u32::decode::<B>(&src[..4]) | u32::decode::<B>(&src[4..]) << 32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{BigEndian, LittleEndian},
+    };
+
+    #[test]
+    fn encode() {
+        let mut dst = [0u8; 8];
+        u16::encode::<LittleEndian>(0x1234_5678, &mut dst[..2]);
+        assert_eq!(dst[..2], [0x78, 0x56]);
+        assert_eq!(u16::decode::<LittleEndian>(&dst[..2]), 0x5678);
+        u16::encode::<BigEndian>(0x1234_5678, &mut dst[..2]);
+        assert_eq!(dst[..2], [0x56, 0x78]);
+        assert_eq!(u16::decode::<BigEndian>(&dst[..2]), 0x5678);
+
+        u32::encode::<BigEndian>(0x1234_5678, &mut dst[..4]);
+        assert_eq!(dst[..4], [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(u32::decode::<BigEndian>(&dst[..4]), 0x1234_5678);
+
+        u64::encode::<LittleEndian>(0x0102_0304_0506_0708, &mut dst);
+        assert_eq!(dst, [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(u64::decode::<LittleEndian>(&dst), 0x0102_0304_0506_0708);
+        u64::encode::<BigEndian>(0x0102_0304_0506_0708, &mut dst);
+        assert_eq!(dst, [5, 6, 7, 8, 1, 2, 3, 4]);
+        assert_eq!(u64::decode::<BigEndian>(&dst), 0x0102_0304_0506_0708);
+    }
+
+    #[test]
+    fn from_hash() {
+        assert_eq!(u16::from_hash(0x1234_5678), 0x5678);
+        assert_eq!(u16::from_hash(0xffff), 0xfffe);
+        assert_eq!(u32::from_hash(0x1_ffff_ffff), 0xffff_fffe);
+        assert_eq!(u64::from_hash(0x1_ffff_ffff), 0x1_ffff_ffff);
+        assert_eq!(u64::from_hash(u64::MAX), u64::MAX - 1);
+    }
+}
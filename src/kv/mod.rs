@@ -5,25 +5,127 @@ use core::{
     slice,
 };
 
+mod byte_order;
+mod crc;
 mod datastore;
-pub use datastore::*;
+mod entry;
+mod key_width;
+pub use {
+    byte_order::{BigEndian, ByteOrder, LittleEndian},
+    datastore::*,
+    entry::Entry,
+    key_width::KeyWidth,
+};
 
 const SIZE_SZ: u32 = size_of::<u32>() as u32;
 const AMOUNT_SZ: u32 = size_of::<u32>() as u32;
-const KEY_SZ: u32 = size_of::<u32>() as u32;
-const META_SZ: u32 = KEY_SZ + SIZE_SZ;
+/// Magic number identifying a formatted store
+const MAGIC: [u8; 4] = *b"HDKV";
+/// Version of the store layout, bumped on incompatible changes
+const VERSION: u8 = 1;
+/// Magic, version, layout flags, key width and a reserved byte
+const PREAMBLE_SZ: u32 = 8;
+/// Layout flags, set for every feature or parameter that changes the layout
+const TYPE_TAG_FLAG: u8 = 1 << 0;
+const CRC_FLAG: u8 = 1 << 1;
+const JOURNAL_FLAG: u8 = 1 << 2;
+const BIG_ENDIAN_FLAG: u8 = 1 << 3;
+const SIZE_ADDR: u32 = PREAMBLE_SZ;
+const AMOUNT_ADDR: u32 = SIZE_ADDR + SIZE_SZ;
+const JOURNAL_ADDR: u32 = AMOUNT_ADDR + AMOUNT_SZ;
+/// State, size, amount, address, length and progress
+#[cfg(feature = "journal")]
+const JOURNAL_SZ: u32 = 6 * size_of::<u32>() as u32;
+#[cfg(not(feature = "journal"))]
+const JOURNAL_SZ: u32 = 0;
+const HEADER_SZ: u32 = PREAMBLE_SZ + SIZE_SZ + AMOUNT_SZ + JOURNAL_SZ;
+/// Journal states
+const IDLE: u32 = 0;
+const APPEND: u32 = 1;
+const FORGET: u32 = 2;
+const REMOVE: u32 = 3;
+const REUSE: u32 = 4;
+#[cfg(feature = "type-tag")]
+const TAG_SZ: u32 = size_of::<u32>() as u32;
+#[cfg(not(feature = "type-tag"))]
+const TAG_SZ: u32 = 0;
+#[cfg(feature = "crc")]
+const CRC_SZ: u32 = size_of::<u32>() as u32;
+#[cfg(not(feature = "crc"))]
+const CRC_SZ: u32 = 0;
+/// Type tag of values inserted as raw bytes
+const BYTES_TAG: u32 = 0;
 
 pub trait KvDataAccess {
     type Error;
     fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error>;
     fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error>;
+    /// Total size of the store in bytes, `u32::MAX` if unbounded
+    fn capacity(&self) -> u32 {
+        u32::MAX
+    }
+
+    /// Make sure all writes have reached the underlying storage
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Make room for at least `len` bytes, for stores that grow on write
+    fn reserve(&mut self, len: u32) -> Result<(), Self::Error> {
+        let _ = len;
+        Ok(())
+    }
+
+    /// Overwrite `len` bytes starting at `address` with zeros
+    fn erase(&mut self, address: u32, len: u32) -> Result<(), Self::Error> {
+        let zeros = [0u8; 32];
+        let mut erased = 0;
+        while erased < len {
+            let chunk = zeros.len().min((len - erased) as usize);
+            self.write_exact(address + erased, &zeros[..chunk])?;
+            erased += chunk as u32;
+        }
+        Ok(())
+    }
+
+    /// Read until `dst` is full
+    fn read_exact(&self, address: u32, dst: &mut [u8]) -> Result<(), Self::Error> {
+        let mut read_len = 0;
+        while read_len < dst.len() {
+            read_len += self.read(address + read_len as u32, &mut dst[read_len..])?;
+        }
+        Ok(())
+    }
+
+    /// Write all of `data`
+    fn write_exact(&mut self, address: u32, data: &[u8]) -> Result<(), Self::Error> {
+        let mut written_len = 0;
+        while written_len < data.len() {
+            written_len += self.write(address + written_len as u32, &data[written_len..])?;
+        }
+        Ok(())
+    }
+}
+
+/// A datastore whose contents are directly addressable in memory
+pub trait SliceBackedStore: KvDataAccess {
+    fn as_bytes(&self) -> &[u8];
+    fn as_bytes_mut(&mut self) -> &mut [u8];
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum KvError<StoreError> {
     Conflict,
     NotFound,
     SizeMismatch,
+    TypeMismatch,
+    Misaligned,
+    Corrupt,
+    BadFormat,
+    LayoutMismatch,
+    ValueTooLarge,
+    Truncated,
     Store(StoreError),
 }
 
@@ -33,23 +135,104 @@ impl<StoreError> From<StoreError> for KvError<StoreError> {
     }
 }
 
+impl<StoreError> KvError<StoreError> {
+    /// Convert the store error, for errors from another store
+    fn convert<E: From<StoreError>>(self) -> KvError<E> {
+        match self {
+            Self::Conflict => KvError::Conflict,
+            Self::NotFound => KvError::NotFound,
+            Self::SizeMismatch => KvError::SizeMismatch,
+            Self::TypeMismatch => KvError::TypeMismatch,
+            Self::Misaligned => KvError::Misaligned,
+            Self::Corrupt => KvError::Corrupt,
+            Self::BadFormat => KvError::BadFormat,
+            Self::LayoutMismatch => KvError::LayoutMismatch,
+            Self::ValueTooLarge => KvError::ValueTooLarge,
+            Self::Truncated => KvError::Truncated,
+            Self::Store(e) => KvError::Store(e.into()),
+        }
+    }
+}
+
+impl<StoreError: core::fmt::Display> core::fmt::Display for KvError<StoreError> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Conflict => f.write_str("key already exists"),
+            Self::NotFound => f.write_str("key not found"),
+            Self::SizeMismatch => f.write_str("value has a different size"),
+            Self::TypeMismatch => f.write_str("value has a different type"),
+            Self::Misaligned => f.write_str("value is not aligned for the type"),
+            Self::Corrupt => f.write_str("value is corrupt"),
+            Self::BadFormat => f.write_str("store is not formatted or has an unsupported version"),
+            Self::LayoutMismatch => {
+                f.write_str("store was formatted with different features, byte order or key width")
+            }
+            Self::ValueTooLarge => f.write_str("value is larger than the maximum value size"),
+            Self::Truncated => f.write_str("store is smaller than its contents"),
+            Self::Store(e) => write!(f, "store error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<StoreError: std::error::Error + 'static> std::error::Error for KvError<StoreError> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Store(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// What `Kv::merge_from` does with keys that exist in both stores
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Keep the existing value
+    #[default]
+    Skip,
+    /// Replace the existing value with the merged one
+    Overwrite,
+}
+
 /// Key-Value store
 ///
 /// Uses the following memory layout:
 /// ```text
-/// |------|--------|---------------|---------------|----
-/// | size | amount | key|size|data | key|size|data | ...
-/// |------|--------|---------------|---------------|----
-/// | header        | value         | value         | ...
-/// |---------------|---------------|---------------|----
+/// |-------|---------|------|--------|---------------|---------------|----
+/// | magic | version | size | amount | key|size|data | key|size|data | ...
+/// |-------|---------|------|--------|---------------|---------------|----
+/// | header                          | value         | value         | ...
+/// |---------------------------------|---------------|---------------|----
 /// ```
-/// The "header" is 8 bytes and consists of a size, and an amount.
-/// Every value has its own header which consists of a key and size totaling 8 bytes.
+/// The "header" is 16 bytes and consists of a 4 byte magic, a 1 byte version, 1 byte of layout flags,
+/// the 1 byte key width, 1 reserved byte, a size, and an amount. The layout flags record the byte
+/// order and which of the `type-tag`, `crc` and `journal` features were enabled. The magic, version
+/// and layout are only written by `Kv::format` and only checked by `Kv::open`.
+/// All sizes, amounts and keys are stored in the byte order `B`, little-endian by default.
+/// Every value has its own header which consists of a key and a 32-bit size. Keys are `W` wide,
+/// 64-bit by default, making the value header 12 bytes. 64-bit keys are stored as two 32-bit words,
+/// low word first.
+/// With the `type-tag` feature, the value header also contains a 32-bit tag identifying the type
+/// of the value, making it 16 bytes. Tags are derived from `TypeId`, so they're only guaranteed
+/// to match within the same build of a program.
+/// With the `crc` feature, the value header also contains a CRC-32 of the data, after the tag.
+/// With the `journal` feature, the header ends with a 24 byte journal recording the operation in
+/// progress, so it can be finished or rolled back by `Kv::recover` after a power loss. Stores
+/// formatted with and without the journal can't be opened by each other. `Kv::compact` isn't
+/// journaled.
 /// Data is dynamically sized.
-pub struct Kv<K, H, S> {
+pub struct Kv<K, H, S, B = LittleEndian, W = u64> {
     _k: PhantomData<K>,
+    _b: PhantomData<B>,
+    _w: PhantomData<W>,
     hasher: H,
     store: S,
+    max_value_size: u32,
+    /// Reuse the slots of forgotten values, see `Kv::set_reuse_freed`
+    reuse_freed: bool,
+    /// Optional in memory map of key to value address
+    #[cfg(feature = "alloc")]
+    index: Option<alloc::collections::BTreeMap<u64, u32>>,
 }
 
 /// Create a new Key-Value store on the heap backed by a Vec. Uses the default hasher from the stdlib.
@@ -71,87 +254,274 @@ impl<K: Hash> Default for Kv<K, std::collections::hash_map::DefaultHasher, HeapD
     }
 }
 
-impl<K, H: Clone, S: Clone> Clone for Kv<K, H, S> {
+impl<K, H: Clone, S: Clone, B, W> Clone for Kv<K, H, S, B, W> {
     fn clone(&self) -> Self {
         Self {
             _k: PhantomData,
+            _b: PhantomData,
+            _w: PhantomData,
             hasher: self.hasher.clone(),
             store: self.store.clone(),
+            max_value_size: self.max_value_size,
+            reuse_freed: self.reuse_freed,
+            #[cfg(feature = "alloc")]
+            index: self.index.clone(),
         }
     }
 }
 
 impl<K: Hash, H: Hasher + Clone, S: KvDataAccess> Kv<K, H, S> {
     pub const fn with_hasher_and_store(hasher: H, store: S) -> Self {
+        Self::with_byte_order(hasher, store)
+    }
+
+    /// Like `with_hasher_and_store`, but keeps an index of all keys in memory to speed up lookups.
+    /// The index is built from the existing contents of the store.
+    #[cfg(feature = "alloc")]
+    pub fn with_index(hasher: H, store: S) -> Result<Self, KvError<S::Error>> {
+        let mut kv = Self::with_hasher_and_store(hasher, store);
+        kv.enable_index()?;
+        Ok(kv)
+    }
+}
+
+impl<K: Hash, H: Hasher + Clone + Default, S: KvDataAccess, B: ByteOrder, W: KeyWidth>
+    Kv<K, H, S, B, W>
+{
+    /// Open an existing store, returning `KvError::BadFormat` if it wasn't created by `Kv::format`
+    /// or has a different layout version, `KvError::LayoutMismatch` if it was formatted with
+    /// different features, byte order or key width, and `KvError::Truncated` if its values don't
+    /// fit in the capacity of the store.
+    /// Interrupted operations are recovered once these checks pass, see `Kv::recover`.
+    pub fn open(store: S) -> Result<Self, KvError<S::Error>> {
+        let mut kv = Self::with_byte_order(H::default(), store);
+        let mut preamble = [0u8; PREAMBLE_SZ as usize];
+        kv.read_all(0, &mut preamble)?;
+        Self::check_preamble(&preamble)?;
+        if kv.size()? > kv.store.capacity().saturating_sub(HEADER_SZ) {
+            return Err(KvError::Truncated);
+        }
+        kv.recover()?;
+        Ok(kv)
+    }
+
+    /// Write a fresh, empty layout to the store, discarding any previous contents.
+    pub fn format(store: S) -> Result<Self, KvError<S::Error>> {
+        let mut kv = Self::with_byte_order(H::default(), store);
+        kv.write_all(0, &Self::preamble())?;
+        kv.reset()?;
+        Ok(kv)
+    }
+
+    /// Create a store where new values are written to the slot of a forgotten value of the same
+    /// size if there is one, instead of being appended. Turn it on for an existing `Kv` with
+    /// `Kv::set_reuse_freed`.
+    ///
+    /// Unlike a free list, no list of forgotten slots is kept in the store: every insert walks
+    /// all values to find the first slot that fits, even with the index enabled, making inserts
+    /// O(n). Only exact fits are reused, so `Kv::compact` is still needed to reclaim the rest.
+    /// Without the `journal` feature, a power loss while a slot is reused can leave a value
+    /// under a partially written key.
+    pub fn with_freelist(store: S) -> Self {
+        let mut kv = Self::with_byte_order(H::default(), store);
+        kv.set_reuse_freed(true);
+        kv
+    }
+}
+
+impl<K, H, S, B, W: KeyWidth> Kv<K, H, S, B, W> {
+    const TAG_OFFSET: u32 = W::SIZE + SIZE_SZ;
+    const CRC_OFFSET: u32 = Self::TAG_OFFSET + TAG_SZ;
+    const META_SZ: u32 = W::SIZE + SIZE_SZ + TAG_SZ + CRC_SZ;
+}
+
+impl<K: Hash, H: Hasher + Clone, S: KvDataAccess, B: ByteOrder, W: KeyWidth> Kv<K, H, S, B, W> {
+    /// Like `with_hasher_and_store`, but with headers stored in the byte order `B` and keys `W` wide.
+    pub const fn with_byte_order(hasher: H, store: S) -> Self {
         Self {
             _k: PhantomData,
+            _b: PhantomData,
+            _w: PhantomData,
             hasher,
             store,
+            max_value_size: u32::MAX,
+            reuse_freed: false,
+            #[cfg(feature = "alloc")]
+            index: None,
         }
     }
 
-    pub fn insert<T: 'static>(&mut self, k: K, v: T) -> Result<(), KvError<S::Error>> {
+    /// Build an in memory index of all keys to speed up lookups, and keep it up to date.
+    #[cfg(feature = "alloc")]
+    pub fn enable_index(&mut self) -> Result<(), KvError<S::Error>> {
+        self.index = Some(Default::default());
+        self.reindex()
+    }
+
+    /// Make inserting a value larger than `max` bytes return `KvError::ValueTooLarge` without
+    /// touching the store. Unlimited by default.
+    pub fn set_max_value_size(&mut self, max: u32) {
+        self.max_value_size = max;
+    }
+
+    /// Turn reusing the slots of forgotten values on or off, see `Kv::with_freelist`.
+    /// Disabled by default.
+    pub fn set_reuse_freed(&mut self, reuse: bool) {
+        self.reuse_freed = reuse;
+    }
+
+    pub fn insert<T: Copy + 'static>(&mut self, k: K, v: T) -> Result<(), KvError<S::Error>> {
+        self.check_value_size(size_of::<T>())?;
         let key = self.hash_key(&k);
 
         if self.find(key)?.is_some() {
             return Err(KvError::Conflict);
         }
 
-        let size = size_of::<T>();
-        let ptr = &v as *const _ as *const u8;
-        let slice = unsafe { slice::from_raw_parts(ptr, size) };
-        let addr = self.size()? + META_SZ;
-        self.write_u32(addr, key)?;
-        self.write_u32(addr + KEY_SZ, size as u32)?;
-        self.write_all(addr + META_SZ, slice)?;
-        self.amount_inc(1)?;
-        self.size_inc(META_SZ + size as u32)?;
-
-        mem::forget(v);
-
-        Ok(())
+        self.append(key, type_tag::<T>(), bytes_of(&v))
     }
 
-    pub fn update<T: 'static>(&mut self, k: K, v: T) -> Result<(), KvError<S::Error>> {
+    pub fn update<T: Copy + 'static>(&mut self, k: K, v: T) -> Result<(), KvError<S::Error>> {
         let key = self.hash_key(&k);
         let found_addr = match self.find(key)? {
             Some(a) => a,
             None => return Err(KvError::NotFound),
         };
-        let found_size = self.read_u32(found_addr + KEY_SZ)? as usize;
-        let size = size_of::<T>();
+        self.check_type::<T>(found_addr)?;
+        self.write_value(found_addr, bytes_of(&v))
+    }
 
-        if found_size != size {
-            return Err(KvError::SizeMismatch);
+    /// Insert or update a value. Unlike `update`, the size of the value may change,
+    /// in which case the old value is removed and the new one is appended.
+    pub fn upsert<T: Copy + 'static>(&mut self, k: K, v: T) -> Result<(), KvError<S::Error>> {
+        self.check_value_size(size_of::<T>())?;
+        let key = self.hash_key(&k);
+
+        if let Some(addr) = self.find(key)? {
+            match self.check_type::<T>(addr) {
+                Ok(()) => return self.write_value(addr, bytes_of(&v)),
+                Err(KvError::SizeMismatch | KvError::TypeMismatch) => self.remove_at(addr)?,
+                Err(e) => return Err(e),
+            }
         }
 
-        let ptr = &v as *const _ as *const u8;
-        let slice = unsafe { slice::from_raw_parts(ptr, size) };
-        self.write_all(found_addr + META_SZ, slice)?;
+        self.append(key, type_tag::<T>(), bytes_of(&v))
+    }
 
-        Ok(())
+    /// Insert or update a value, returning the previous value if there was one.
+    /// The previous value must have the same type.
+    pub fn replace<T: Copy + 'static>(
+        &mut self,
+        k: K,
+        v: T,
+    ) -> Result<Option<T>, KvError<S::Error>> {
+        let key = self.hash_key(&k);
+        match self.find(key)? {
+            Some(addr) => {
+                let old = self.read_value(addr)?;
+                self.write_value(addr, bytes_of(&v))?;
+                Ok(Some(old))
+            }
+            None => {
+                self.append(key, type_tag::<T>(), bytes_of(&v))?;
+                Ok(None)
+            }
+        }
     }
 
-    pub fn get<T: 'static>(&mut self, k: K) -> Result<Option<T>, KvError<S::Error>> {
+    /// Modify a value in place with `f`. Returns `false` if the key doesn't exist.
+    pub fn modify<T: Copy + 'static, F: FnOnce(&mut T)>(
+        &mut self,
+        k: K,
+        f: F,
+    ) -> Result<bool, KvError<S::Error>> {
+        let key = self.hash_key(&k);
+        let addr = match self.find(key)? {
+            Some(a) => a,
+            None => return Ok(false),
+        };
+        let mut v = self.read_value::<T>(addr)?;
+        f(&mut v);
+        self.write_value(addr, bytes_of(&v))?;
+        Ok(true)
+    }
+
+    pub fn get<T: Copy + 'static>(&mut self, k: K) -> Result<Option<T>, KvError<S::Error>> {
         let key = self.hash_key(&k);
         let found_addr = match self.find(key)? {
             Some(a) => a,
             None => return Ok(None),
         };
-        let found_size = self.read_u32(found_addr + KEY_SZ)? as usize;
-        let size = size_of::<T>();
 
-        if found_size != size {
+        Ok(Some(self.read_value(found_addr)?))
+    }
+
+    /// Remove a value and return it. Memory is reclaimed by moving all values after it down.
+    pub fn remove<T: Copy + 'static>(&mut self, k: K) -> Result<Option<T>, KvError<S::Error>> {
+        let key = self.hash_key(&k);
+        let addr = match self.find(key)? {
+            Some(a) => a,
+            None => return Ok(None),
+        };
+        let v = self.read_value(addr)?;
+        self.remove_at(addr)?;
+        Ok(Some(v))
+    }
+
+    /// Insert a dynamically sized value.
+    pub fn insert_bytes(&mut self, k: K, bytes: &[u8]) -> Result<(), KvError<S::Error>> {
+        let key = self.hash_key(&k);
+
+        if self.find(key)?.is_some() {
+            return Err(KvError::Conflict);
+        }
+
+        self.append(key, BYTES_TAG, bytes)
+    }
+
+    /// Read a value into `dst`, returning the size of the value.
+    /// Returns `KvError::SizeMismatch` if `dst` is smaller than the value.
+    pub fn get_bytes_into(&self, k: K, dst: &mut [u8]) -> Result<Option<usize>, KvError<S::Error>> {
+        let key = self.hash_key(&k);
+        let addr = match self.find(key)? {
+            Some(a) => a,
+            None => return Ok(None),
+        };
+        let size = self.read_u32(addr + W::SIZE)? as usize;
+
+        if dst.len() < size {
             return Err(KvError::SizeMismatch);
         }
 
-        let mut v = MaybeUninit::<T>::uninit();
-        let ptr = &mut v as *mut _ as *mut u8;
-        let slice = unsafe { slice::from_raw_parts_mut(ptr, size) };
+        self.read_all(addr + Self::META_SZ, &mut dst[..size])?;
+        self.check_crc(addr, &dst[..size])?;
+
+        Ok(Some(size))
+    }
+
+    /// Size of a value in bytes
+    pub fn value_size(&self, k: K) -> Result<Option<u32>, KvError<S::Error>> {
+        let key = self.hash_key(&k);
+        match self.find(key)? {
+            Some(addr) => Ok(Some(self.read_u32(addr + W::SIZE)?)),
+            None => Ok(None),
+        }
+    }
 
-        self.read_all(found_addr + META_SZ, slice)?;
+    /// Get the entry for a key, for in place insertion or modification.
+    #[allow(clippy::type_complexity)]
+    pub fn entry(&mut self, k: K) -> Result<Entry<'_, K, H, S, B, W>, KvError<S::Error>> {
+        let key = self.hash_key(&k);
+        Entry::new(self, key)
+    }
 
-        Ok(Some(unsafe { v.assume_init() }))
+    /// Get a value, inserting the result of `f` first if it doesn't exist.
+    pub fn get_or_insert_with<T: Copy + 'static, F: FnOnce() -> T>(
+        &mut self,
+        k: K,
+        f: F,
+    ) -> Result<T, KvError<S::Error>> {
+        self.entry(k)?.or_insert_with(f)
     }
 
     /// Forget a value. Memory is not returned. This just frees up the key/type.
@@ -161,18 +531,145 @@ impl<K: Hash, H: Hasher + Clone, S: KvDataAccess> Kv<K, H, S> {
             Some(a) => a,
             None => return Err(KvError::NotFound),
         };
-        let size = self.read_u32(addr + KEY_SZ)?;
-
-        // Keep the size as it is needed
-        // Key
-        self.write_u32(addr, u32::MAX)?;
-        // Data
-        let mut ptr = addr + META_SZ;
-        while ptr < addr + META_SZ + size {
-            self.write_all(ptr, &[u8::MAX])?;
-            ptr += 1;
+        let size = self.read_u32(addr + W::SIZE)?;
+        #[cfg(feature = "alloc")]
+        if let Some(index) = &mut self.index {
+            index.remove(&key);
+        }
+        self.begin(FORGET, addr, size)?;
+        self.finish_forget(addr, size)?;
+        self.commit()
+    }
+
+    /// Finish or roll back an operation that was interrupted, for example by a power loss, leaving
+    /// the store consistent. Inserts are rolled back, including ones reusing the slot of a
+    /// forgotten value, while forgets and removes are finished.
+    /// Does nothing without the `journal` feature.
+    pub fn recover(&mut self) -> Result<(), KvError<S::Error>> {
+        if !cfg!(feature = "journal") {
+            return Ok(());
+        }
+        let mut journal = [0u32; 6];
+        for (i, v) in journal.iter_mut().enumerate() {
+            *v = self.read_u32(JOURNAL_ADDR + 4 * i as u32)?;
+        }
+        let [state, size, amount, addr, len, progress] = journal;
+        match state {
+            APPEND => {
+                self.write_u32(AMOUNT_ADDR, amount)?;
+                self.write_u32(SIZE_ADDR, size)?;
+            }
+            FORGET => self.finish_forget(addr, len)?,
+            REMOVE => self.finish_remove(addr, len, size, amount, progress)?,
+            REUSE => self.finish_forget(addr, len)?,
+            _ => return Ok(()),
+        }
+        self.commit()?;
+        self.reindex()
+    }
+
+    /// Reclaim the memory of all forgotten values by moving the remaining values together.
+    /// Not journaled, even with the `journal` feature: a power loss while values are being moved
+    /// can leave the store corrupt.
+    pub fn compact(&mut self) -> Result<(), KvError<S::Error>> {
+        let amount = self.amount()?;
+        let mut src = HEADER_SZ;
+        let mut dst = HEADER_SZ;
+        let mut live = 0;
+
+        for _ in 0..amount {
+            let key = self.read_key(src)?;
+            let entry_size = Self::META_SZ + self.read_u32(src + W::SIZE)?;
+            if key != W::TOMBSTONE {
+                if src != dst {
+                    self.move_down(src, dst, entry_size)?;
+                }
+                dst += entry_size;
+                live += 1;
+            }
+            src += entry_size;
+        }
+
+        self.write_u32(SIZE_ADDR, dst - HEADER_SZ)?;
+        self.write_u32(AMOUNT_ADDR, live)?;
+        self.reindex()
+    }
+
+    /// Iterate over the key hash and size of every value that hasn't been forgotten.
+    pub fn iter(&self) -> impl Iterator<Item = Result<(u64, u32), KvError<S::Error>>> + '_ {
+        self.entries().filter_map(|entry| match entry {
+            Ok((_, key, _)) if key == W::TOMBSTONE => None,
+            Ok((_, key, size)) => Some(Ok((key, size))),
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// Iterate over the key hash and value of every entry, which all have to be a `T`.
+    pub fn iter_typed<T: Copy + 'static>(
+        &self,
+    ) -> impl Iterator<Item = Result<(u64, T), KvError<S::Error>>> + '_ {
+        self.entries().filter_map(|entry| match entry {
+            Ok((_, key, _)) if key == W::TOMBSTONE => None,
+            Ok((addr, key, _)) => Some(self.read_value(addr).map(|v| (key, v))),
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// Copy every value that hasn't been forgotten from `other`, returning how many keys were
+    /// inserted. Values replacing an existing key with `MergePolicy::Overwrite` aren't counted.
+    /// Both stores must hash keys the same way, i.e. use the same hasher with the same state.
+    /// Errors from `other` are converted to the error of this store.
+    pub fn merge_from<S2: KvDataAccess>(
+        &mut self,
+        other: &Kv<K, H, S2, B, W>,
+        policy: MergePolicy,
+    ) -> Result<usize, KvError<S::Error>>
+    where
+        S::Error: From<S2::Error>,
+    {
+        let mut inserted = 0;
+        for entry in other.entries() {
+            let (src, key, size) = entry.map_err(KvError::convert)?;
+            if key == W::TOMBSTONE {
+                continue;
+            }
+            match self.find(key)? {
+                Some(addr) => match policy {
+                    MergePolicy::Skip => continue,
+                    MergePolicy::Overwrite => self.remove_at(addr)?,
+                },
+                None => inserted += 1,
+            }
+            self.append_from(other, src, key, size)?;
+        }
+        Ok(inserted)
+    }
+
+    /// Check the CRC of every value, returning `KvError::Corrupt` if any of them doesn't match.
+    /// Always succeeds without the `crc` feature.
+    pub fn verify(&self) -> Result<(), KvError<S::Error>> {
+        if !cfg!(feature = "crc") {
+            return Ok(());
         }
 
+        let mut buf = [0u8; 32];
+        for entry in self.entries() {
+            let (addr, key, size) = entry?;
+            if key == W::TOMBSTONE {
+                continue;
+            }
+            let mut crc = 0;
+            let mut read = 0;
+            while read < size {
+                let chunk = buf.len().min((size - read) as usize);
+                self.read_all(addr + Self::META_SZ + read, &mut buf[..chunk])?;
+                crc = crc::crc32(crc, &buf[..chunk]);
+                read += chunk as u32;
+            }
+            if self.read_u32(addr + Self::CRC_OFFSET)? != crc {
+                return Err(KvError::Corrupt);
+            }
+        }
         Ok(())
     }
 
@@ -181,18 +678,111 @@ impl<K: Hash, H: Hasher + Clone, S: KvDataAccess> Kv<K, H, S> {
         Ok(self.find(key)?.is_some())
     }
 
+    /// Like `exists`, but only returns `true` if the value can be read as a `T`.
+    pub fn contains_typed<T: 'static>(&self, k: K) -> Result<bool, KvError<S::Error>> {
+        let key = self.hash_key(&k);
+        let addr = match self.find(key)? {
+            Some(a) => a,
+            None => return Ok(false),
+        };
+        match self.check_type::<T>(addr) {
+            Ok(()) => Ok(true),
+            Err(KvError::SizeMismatch | KvError::TypeMismatch) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn reset(&mut self) -> Result<(), KvError<S::Error>> {
-        self.write_u32(0, 0)?;
-        self.write_u32(4, 0)?;
-        Ok(())
+        self.write_u32(SIZE_ADDR, 0)?;
+        self.write_u32(AMOUNT_ADDR, 0)?;
+        self.commit()?;
+        self.reindex()
+    }
+
+    /// Like `reset`, but also erases all values so no stale data is left in the store.
+    pub fn secure_reset(&mut self) -> Result<(), KvError<S::Error>> {
+        let size = self.size()?;
+        self.reset()?;
+        Ok(self.store.erase(HEADER_SZ, size)?)
+    }
+
+    /// Copy the header and all values into `dst`, returning the number of bytes copied.
+    /// Returns `KvError::SizeMismatch` if `dst` is too small.
+    pub fn export_into(&self, dst: &mut [u8]) -> Result<usize, KvError<S::Error>> {
+        let len = (HEADER_SZ + self.size()?) as usize;
+        if dst.len() < len {
+            return Err(KvError::SizeMismatch);
+        }
+        self.read_all(0, &mut dst[..len])?;
+        Ok(len)
+    }
+
+    /// Replace the contents of the store with an image created by `export_into`.
+    /// The image is checked like `Kv::open` checks a store before anything is written.
+    /// Returns `KvError::BadFormat` if the length of `src` doesn't match its header, and
+    /// `KvError::Truncated` if it doesn't fit in the capacity of the store.
+    pub fn import_from(&mut self, src: &[u8]) -> Result<(), KvError<S::Error>> {
+        let header = src.get(..HEADER_SZ as usize).ok_or(KvError::BadFormat)?;
+        Self::check_preamble(&header[..PREAMBLE_SZ as usize])?;
+        let mut size = [0u8; SIZE_SZ as usize];
+        size.copy_from_slice(&header[SIZE_ADDR as usize..][..SIZE_SZ as usize]);
+        if src.len() != (HEADER_SZ + B::from_bytes(size)) as usize {
+            return Err(KvError::BadFormat);
+        }
+        if src.len() > self.store.capacity() as usize {
+            return Err(KvError::Truncated);
+        }
+
+        self.write_all(0, src)?;
+        self.recover()?;
+        self.reindex()
     }
 
     pub fn size(&self) -> Result<u32, KvError<S::Error>> {
-        self.read_u32(0)
+        self.read_u32(SIZE_ADDR)
+    }
+
+    /// Bytes used by values, excluding the header. Same as `size`.
+    pub fn used_space(&self) -> Result<u32, KvError<S::Error>> {
+        self.size()
+    }
+
+    /// Bytes left in the store for new values, including their headers
+    pub fn free_space(&self) -> Result<u32, KvError<S::Error>> {
+        let used = HEADER_SZ + self.size()?;
+        Ok(self.store.capacity().saturating_sub(used))
     }
 
     pub fn amount(&self) -> Result<u32, KvError<S::Error>> {
-        self.read_u32(4)
+        self.read_u32(AMOUNT_ADDR)
+    }
+
+    /// Number of values that haven't been forgotten. Unlike `amount`, this walks all values.
+    pub fn len(&self) -> Result<u32, KvError<S::Error>> {
+        let mut len = 0;
+        for entry in self.iter() {
+            entry?;
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, KvError<S::Error>> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Make room for values taking up `additional` bytes, including their headers,
+    /// see `KvDataAccess::reserve`
+    pub fn reserve(&mut self, additional: u32) -> Result<(), KvError<S::Error>> {
+        let len = (HEADER_SZ + self.size()?)
+            .checked_add(additional)
+            .ok_or(KvError::ValueTooLarge)?;
+        Ok(self.store.reserve(len)?)
+    }
+
+    /// Flush the store, see `KvDataAccess::flush`
+    pub fn flush(&mut self) -> Result<(), KvError<S::Error>> {
+        Ok(self.store.flush()?)
     }
 
     pub fn store(&mut self) -> &mut S {
@@ -203,84 +793,1573 @@ impl<K: Hash, H: Hasher + Clone, S: KvDataAccess> Kv<K, H, S> {
         &mut self.hasher
     }
 
-    fn find(&self, key: u32) -> Result<Option<u32>, KvError<S::Error>> {
-        let amount = self.amount()?;
-        let mut addr = SIZE_SZ + AMOUNT_SZ;
-        let mut idx = 0;
-
-        while idx < amount {
-            let found_key = self.read_u32(addr)?;
-            let size = self.read_u32(addr + KEY_SZ)?;
+    fn find(&self, key: u64) -> Result<Option<u32>, KvError<S::Error>> {
+        #[cfg(feature = "alloc")]
+        if let Some(index) = &self.index {
+            return Ok(index.get(&key).copied());
+        }
 
+        for entry in self.entries() {
+            let (addr, found_key, _) = entry?;
             if key == found_key {
                 return Ok(Some(addr));
-            } else {
-                addr += META_SZ + size;
-                idx += 1;
             }
         }
 
         Ok(None)
     }
 
+    /// Walk all values, including forgotten ones, yielding their address, key and size.
+    /// Stops after the first error.
+    fn entries(&self) -> impl Iterator<Item = Result<(u32, u64, u32), KvError<S::Error>>> + '_ {
+        let mut addr = HEADER_SZ;
+        let mut remaining = self.amount();
+
+        core::iter::from_fn(move || {
+            let left = match remaining {
+                Ok(0) => return None,
+                Ok(left) => left,
+                Err(_) => return mem::replace(&mut remaining, Ok(0)).err().map(Err),
+            };
+            let entry = self
+                .read_key(addr)
+                .and_then(|key| Ok((addr, key, self.read_u32(addr + W::SIZE)?)));
+            match entry {
+                Ok((_, _, size)) => {
+                    addr += Self::META_SZ + size;
+                    remaining = Ok(left - 1);
+                }
+                Err(_) => remaining = Ok(0),
+            }
+            Some(entry)
+        })
+    }
+
     fn size_inc(&mut self, inc: u32) -> Result<u32, KvError<S::Error>> {
         let old_size = self.size()?;
         let new_size = old_size + inc;
-        self.write_u32(0, new_size)?;
+        self.write_u32(SIZE_ADDR, new_size)?;
         Ok(new_size)
     }
 
     fn amount_inc(&mut self, inc: u32) -> Result<u32, KvError<S::Error>> {
         let old_size = self.amount()?;
         let new_amount = old_size + inc;
-        self.write_u32(4, new_amount)?;
+        self.write_u32(AMOUNT_ADDR, new_amount)?;
         Ok(new_amount)
     }
 
-    fn read_u32(&self, address: u32) -> Result<u32, KvError<S::Error>> {
-        let mut v = [0u8; size_of::<u32>()];
-        self.read_all(address, &mut v)?;
-        Ok(u32::from_ne_bytes(v))
+    /// Check that the value at `addr` has the size, and type tag if enabled, of `T`
+    fn check_type<T: 'static>(&self, addr: u32) -> Result<(), KvError<S::Error>> {
+        if self.read_u32(addr + W::SIZE)? as usize != size_of::<T>() {
+            return Err(KvError::SizeMismatch);
+        }
+        if cfg!(feature = "type-tag") && self.read_u32(addr + Self::TAG_OFFSET)? != type_tag::<T>()
+        {
+            return Err(KvError::TypeMismatch);
+        }
+        Ok(())
     }
 
-    fn write_u32(&mut self, address: u32, value: u32) -> Result<(), KvError<S::Error>> {
-        self.write_all(address, &value.to_ne_bytes())
+    fn check_value_size(&self, size: usize) -> Result<(), KvError<S::Error>> {
+        match size > self.max_value_size as usize {
+            true => Err(KvError::ValueTooLarge),
+            false => Ok(()),
+        }
     }
 
-    fn read_all(&self, address: u32, dst: &mut [u8]) -> Result<(), KvError<S::Error>> {
-        let mut read_len = 0;
-        while read_len < dst.len() {
-            read_len += self
-                .store
-                .read(address + read_len as u32, &mut dst[read_len..])?;
+    fn read_value<T: Copy + 'static>(&self, addr: u32) -> Result<T, KvError<S::Error>> {
+        self.check_type::<T>(addr)?;
+
+        let size = size_of::<T>();
+        let mut v = MaybeUninit::<T>::uninit();
+        let ptr = &mut v as *mut _ as *mut u8;
+        let slice = unsafe { slice::from_raw_parts_mut(ptr, size) };
+
+        self.read_all(addr + Self::META_SZ, slice)?;
+        self.check_crc(addr, slice)?;
+
+        Ok(unsafe { v.assume_init() })
+    }
+
+    /// Write a new value after the last one
+    fn append(&mut self, key: u64, tag: u32, data: &[u8]) -> Result<(), KvError<S::Error>> {
+        self.check_value_size(data.len())?;
+        if self.reuse_freed && self.reuse_slot(key, tag, data)? {
+            return Ok(());
+        }
+        let size = data.len() as u32;
+        let addr = self.size()? + HEADER_SZ;
+        self.begin(APPEND, addr, size)?;
+        self.write_key(addr, key)?;
+        self.write_u32(addr + W::SIZE, size)?;
+        if cfg!(feature = "type-tag") {
+            self.write_u32(addr + Self::TAG_OFFSET, tag)?;
+        }
+        self.write_value(addr, data)?;
+        self.amount_inc(1)?;
+        self.size_inc(Self::META_SZ + size)?;
+        self.commit()?;
+        #[cfg(feature = "alloc")]
+        if let Some(index) = &mut self.index {
+            index.insert(key, addr);
         }
         Ok(())
     }
 
-    fn write_all(&mut self, address: u32, data: &[u8]) -> Result<(), KvError<S::Error>> {
-        let mut written_len = 0;
-        while written_len < data.len() {
-            written_len += self
-                .store
-                .write(address + written_len as u32, &data[written_len..])?;
+    /// Write a new value to the first slot of a forgotten value of the same size, if there is one.
+    /// The key is written last, and with the `journal` feature an interrupted write is rolled back
+    /// by forgetting the slot again.
+    fn reuse_slot(&mut self, key: u64, tag: u32, data: &[u8]) -> Result<bool, KvError<S::Error>> {
+        let mut slot = None;
+        for entry in self.entries() {
+            match entry? {
+                (addr, k, size) if k == W::TOMBSTONE && size as usize == data.len() => {
+                    slot = Some(addr);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let addr = match slot {
+            Some(a) => a,
+            None => return Ok(false),
+        };
+
+        self.begin(REUSE, addr, data.len() as u32)?;
+        if cfg!(feature = "type-tag") {
+            self.write_u32(addr + Self::TAG_OFFSET, tag)?;
+        }
+        self.write_value(addr, data)?;
+        self.write_key(addr, key)?;
+        self.commit()?;
+        #[cfg(feature = "alloc")]
+        if let Some(index) = &mut self.index {
+            index.insert(key, addr);
+        }
+        Ok(true)
+    }
+
+    /// Like `append`, but copies the value at `src` in `other` as is, including its size, tag
+    /// and CRC
+    fn append_from<S2: KvDataAccess>(
+        &mut self,
+        other: &Kv<K, H, S2, B, W>,
+        src: u32,
+        key: u64,
+        size: u32,
+    ) -> Result<(), KvError<S::Error>>
+    where
+        S::Error: From<S2::Error>,
+    {
+        self.check_value_size(size as usize)?;
+        let addr = self.size()? + HEADER_SZ;
+        self.begin(APPEND, addr, size)?;
+        self.write_key(addr, key)?;
+        let mut buf = [0u8; 32];
+        let mut copied = W::SIZE;
+        while copied < Self::META_SZ + size {
+            let chunk = buf.len().min((Self::META_SZ + size - copied) as usize);
+            other
+                .read_all(src + copied, &mut buf[..chunk])
+                .map_err(KvError::convert)?;
+            self.write_all(addr + copied, &buf[..chunk])?;
+            copied += chunk as u32;
+        }
+        self.amount_inc(1)?;
+        self.size_inc(Self::META_SZ + size)?;
+        self.commit()?;
+        #[cfg(feature = "alloc")]
+        if let Some(index) = &mut self.index {
+            index.insert(key, addr);
         }
         Ok(())
     }
 
-    fn hash_key(&self, t: &K) -> u32 {
-        let mut hasher = self.hasher.clone();
-        (*t).hash(&mut hasher);
-        hasher.finish() as u32
+    /// Write the data of the value at `addr`, updating its CRC if enabled
+    fn write_value(&mut self, addr: u32, data: &[u8]) -> Result<(), KvError<S::Error>> {
+        if cfg!(feature = "crc") {
+            self.write_u32(addr + Self::CRC_OFFSET, crc::crc32(0, data))?;
+        }
+        self.write_all(addr + Self::META_SZ, data)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Check `data` against the CRC of the value at `addr`, if enabled
+    fn check_crc(&self, addr: u32, data: &[u8]) -> Result<(), KvError<S::Error>> {
+        if cfg!(feature = "crc") && self.read_u32(addr + Self::CRC_OFFSET)? != crc::crc32(0, data) {
+            return Err(KvError::Corrupt);
+        }
+        Ok(())
+    }
+
+    /// Remove the value at `addr`, moving all values after it down
+    fn remove_at(&mut self, addr: u32) -> Result<(), KvError<S::Error>> {
+        let entry_size = Self::META_SZ + self.read_u32(addr + W::SIZE)?;
+        let (size, amount) = (self.size()?, self.amount()?);
+        self.begin(REMOVE, addr, entry_size)?;
+        self.finish_remove(addr, entry_size, size, amount, 0)?;
+        self.commit()?;
+        self.reindex()
+    }
+
+    /// Tombstone the value at `addr` and overwrite its `size` bytes of data
+    fn finish_forget(&mut self, addr: u32, size: u32) -> Result<(), KvError<S::Error>> {
+        self.write_key(addr, W::TOMBSTONE)?;
+        let mut ptr = addr + Self::META_SZ;
+        while ptr < addr + Self::META_SZ + size {
+            self.write_all(ptr, &[u8::MAX])?;
+            ptr += 1;
+        }
+        Ok(())
+    }
+
+    /// Move the values after the one at `addr` down, starting `progress` bytes in, and update the
+    /// header from the `size` and `amount` before the removal.
+    fn finish_remove(
+        &mut self,
+        addr: u32,
+        entry_size: u32,
+        size: u32,
+        amount: u32,
+        progress: u32,
+    ) -> Result<(), KvError<S::Error>> {
+        let next = addr + entry_size;
+        let len = HEADER_SZ + size - next;
+        // Chunks are never larger than the gap, so an interrupted chunk can be moved again
+        // without reading data it already overwrote.
+        let mut buf = [0u8; 32];
+        let mut moved = progress;
+        while moved < len {
+            let chunk = buf
+                .len()
+                .min(entry_size as usize)
+                .min((len - moved) as usize);
+            self.read_all(next + moved, &mut buf[..chunk])?;
+            self.write_all(addr + moved, &buf[..chunk])?;
+            moved += chunk as u32;
+            if cfg!(feature = "journal") {
+                self.write_u32(JOURNAL_ADDR + 20, moved)?;
+            }
+        }
+        self.write_u32(AMOUNT_ADDR, amount - 1)?;
+        self.write_u32(SIZE_ADDR, size - entry_size)
+    }
+
+    /// Record the operation about to be done in the journal, if enabled
+    fn begin(&mut self, state: u32, addr: u32, len: u32) -> Result<(), KvError<S::Error>> {
+        if cfg!(feature = "journal") {
+            let (size, amount) = (self.size()?, self.amount()?);
+            for (i, v) in [size, amount, addr, len, 0].into_iter().enumerate() {
+                self.write_u32(JOURNAL_ADDR + 4 * (i as u32 + 1), v)?;
+            }
+            self.write_u32(JOURNAL_ADDR, state)?;
+        }
+        Ok(())
+    }
+
+    /// Mark the operation in the journal as done, if enabled
+    fn commit(&mut self) -> Result<(), KvError<S::Error>> {
+        if cfg!(feature = "journal") {
+            self.write_u32(JOURNAL_ADDR, IDLE)?;
+        }
+        Ok(())
+    }
+
+    /// Rebuild the index, if enabled, after values have been moved
+    fn reindex(&mut self) -> Result<(), KvError<S::Error>> {
+        #[cfg(feature = "alloc")]
+        if self.index.is_some() {
+            let mut index = alloc::collections::BTreeMap::new();
+            for entry in self.entries() {
+                let (addr, key, _) = entry?;
+                if key != W::TOMBSTONE {
+                    index.insert(key, addr);
+                }
+            }
+            self.index = Some(index);
+        }
+        Ok(())
+    }
+
+    /// Move `len` bytes from `src` to `dst`, where `dst` < `src`
+    fn move_down(&mut self, src: u32, dst: u32, len: u32) -> Result<(), KvError<S::Error>> {
+        let mut buf = [0u8; 32];
+        let mut moved = 0;
+        while moved < len {
+            let chunk = buf.len().min((len - moved) as usize);
+            self.read_all(src + moved, &mut buf[..chunk])?;
+            self.write_all(dst + moved, &buf[..chunk])?;
+            moved += chunk as u32;
+        }
+        Ok(())
+    }
+
+    fn read_u32(&self, address: u32) -> Result<u32, KvError<S::Error>> {
+        let mut v = [0u8; size_of::<u32>()];
+        self.read_all(address, &mut v)?;
+        Ok(B::from_bytes(v))
+    }
+
+    fn write_u32(&mut self, address: u32, value: u32) -> Result<(), KvError<S::Error>> {
+        self.write_all(address, &B::to_bytes(value))
+    }
+
+    fn read_key(&self, address: u32) -> Result<u64, KvError<S::Error>> {
+        let mut v = [0u8; size_of::<u64>()];
+        let v = &mut v[..W::SIZE as usize];
+        self.read_all(address, v)?;
+        Ok(W::decode::<B>(v))
+    }
+
+    fn write_key(&mut self, address: u32, key: u64) -> Result<(), KvError<S::Error>> {
+        let mut v = [0u8; size_of::<u64>()];
+        let v = &mut v[..W::SIZE as usize];
+        W::encode::<B>(key, v);
+        self.write_all(address, v)
+    }
+
+    fn read_all(&self, address: u32, dst: &mut [u8]) -> Result<(), KvError<S::Error>> {
+        Ok(self.store.read_exact(address, dst)?)
+    }
+
+    fn write_all(&mut self, address: u32, data: &[u8]) -> Result<(), KvError<S::Error>> {
+        Ok(self.store.write_exact(address, data)?)
+    }
+
+    /// The preamble `format` writes for this combination of features, byte order and key width
+    fn preamble() -> [u8; PREAMBLE_SZ as usize] {
+        let mut flags = 0;
+        if cfg!(feature = "type-tag") {
+            flags |= TYPE_TAG_FLAG;
+        }
+        if cfg!(feature = "crc") {
+            flags |= CRC_FLAG;
+        }
+        if cfg!(feature = "journal") {
+            flags |= JOURNAL_FLAG;
+        }
+        if B::to_bytes(1)[0] != 1 {
+            flags |= BIG_ENDIAN_FLAG;
+        }
+
+        let mut preamble = [0u8; PREAMBLE_SZ as usize];
+        preamble[..4].copy_from_slice(&MAGIC);
+        preamble[4] = VERSION;
+        preamble[5] = flags;
+        preamble[6] = W::SIZE as u8;
+        preamble
+    }
+
+    /// Check a preamble read from a store against the one `format` writes
+    fn check_preamble(preamble: &[u8]) -> Result<(), KvError<S::Error>> {
+        let expected = Self::preamble();
+        if preamble[..5] != expected[..5] {
+            return Err(KvError::BadFormat);
+        }
+        match preamble[5..] != expected[5..] {
+            true => Err(KvError::LayoutMismatch),
+            false => Ok(()),
+        }
+    }
+
+    fn hash_key(&self, t: &K) -> u64 {
+        let mut hasher = self.hasher.clone();
+        (*t).hash(&mut hasher);
+        W::from_hash(hasher.finish())
+    }
+}
+
+/// Identifies the type of a value. Only stable within a build of a program.
+#[cfg(feature = "type-tag")]
+fn type_tag<T: 'static>() -> u32 {
+    /// 32-bit FNV-1a, so tags don't depend on a randomly seeded hasher
+    struct Fnv1a(u32);
+
+    impl Hasher for Fnv1a {
+        fn finish(&self) -> u64 {
+            self.0 as u64
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for b in bytes {
+                self.0 = (self.0 ^ *b as u32).wrapping_mul(0x0100_0193);
+            }
+        }
+    }
+
+    let mut hasher = Fnv1a(0x811c_9dc5);
+    core::any::TypeId::of::<T>().hash(&mut hasher);
+    match hasher.finish() as u32 {
+        // Don't collide with raw bytes
+        BYTES_TAG => BYTES_TAG + 1,
+        tag => tag,
+    }
+}
+
+#[cfg(not(feature = "type-tag"))]
+#[allow(clippy::extra_unused_type_parameters)]
+fn type_tag<T: 'static>() -> u32 {
+    BYTES_TAG
+}
+
+fn bytes_of<T: Copy>(v: &T) -> &[u8] {
+    // SAFETY: v is a valid reference to size_of::<T>() bytes
+    unsafe { slice::from_raw_parts(v as *const T as *const u8, size_of::<T>()) }
+}
+
+#[cfg(feature = "alloc")]
+impl<K: Hash, H: Hasher + Clone, B: ByteOrder, W: KeyWidth> Kv<K, H, HeapDataStore, B, W> {
+    /// Release the memory of the heap store past the stored values. Use `compact` first to also
+    /// release the memory of forgotten values.
+    pub fn shrink_store(&mut self) -> Result<(), KvError<<HeapDataStore as KvDataAccess>::Error>> {
+        let len = self.store.shrink_len((HEADER_SZ + self.size()?) as usize);
+        self.store.shrink_to(len.max(HEADER_SZ as usize));
+        Ok(())
+    }
+}
+
+impl<K: Hash, H: Hasher + Clone, S: SliceBackedStore, B: ByteOrder, W: KeyWidth> Kv<K, H, S, B, W> {
+    /// Get a reference to a value without copying it out of the store.
+    /// Returns `KvError::Misaligned` if the value isn't suitably aligned for `T`.
+    pub fn get_ref<T: Copy + 'static>(&self, k: K) -> Result<Option<&T>, KvError<S::Error>> {
+        let addr = match self.find_value::<T>(k)? {
+            Some(a) => a,
+            None => return Ok(None),
+        };
+        let ptr = self.store.as_bytes()[addr as usize..].as_ptr();
+        match ptr.align_offset(mem::align_of::<T>()) {
+            // SAFETY: The value is size_of::<T>() bytes long and aligned
+            0 => Ok(Some(unsafe { &*(ptr as *const T) })),
+            _ => Err(KvError::Misaligned),
+        }
+    }
+
+    /// Get a mutable reference to a value without copying it out of the store.
+    /// Returns `KvError::Misaligned` if the value isn't suitably aligned for `T`.
+    ///
+    /// With the `crc` feature, changes made through the reference aren't reflected in the
+    /// value's CRC. Write the value back with `update` to keep it valid.
+    pub fn get_mut<T: Copy + 'static>(
+        &mut self,
+        k: K,
+    ) -> Result<Option<&mut T>, KvError<S::Error>> {
+        let addr = match self.find_value::<T>(k)? {
+            Some(a) => a,
+            None => return Ok(None),
+        };
+        let ptr = self.store.as_bytes_mut()[addr as usize..].as_mut_ptr();
+        match ptr.align_offset(mem::align_of::<T>()) {
+            // SAFETY: The value is size_of::<T>() bytes long and aligned
+            0 => Ok(Some(unsafe { &mut *(ptr as *mut T) })),
+            _ => Err(KvError::Misaligned),
+        }
+    }
+
+    /// Find the address of the data of a value of type `T`
+    fn find_value<T: 'static>(&self, k: K) -> Result<Option<u32>, KvError<S::Error>> {
+        let key = self.hash_key(&k);
+        let addr = match self.find(key)? {
+            Some(a) => a,
+            None => return Ok(None),
+        };
+        self.check_type::<T>(addr)?;
+        let data = addr + Self::META_SZ;
+        self.check_crc(
+            addr,
+            &self.store.as_bytes()[data as usize..][..size_of::<T>()],
+        )?;
+        Ok(Some(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::hash::{BuildHasher, DefaultHasher, RandomState},
+    };
+
+    /// Store for tests that don't depend on the store type, available without any features
+    type TestStore = StaticDataStore<16384>;
+
+    fn new_kv<K: Hash>() -> Kv<K, DefaultHasher, TestStore> {
+        Kv::with_hasher_and_store(RandomState::new().build_hasher(), TestStore::new())
+    }
+
+    /// Value header size with the default key width
+    const META_SZ: u32 = Kv::<(), (), (), LittleEndian>::META_SZ;
+
+    /// Hashes integer keys to themselves
+    #[derive(Clone, Default)]
+    struct IdentityHasher(u64);
+
+    impl Hasher for IdentityHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for b in bytes {
+                self.0 = (self.0 << 8) | *b as u64;
+            }
+        }
+
+        fn write_u64(&mut self, i: u64) {
+            self.0 = i;
+        }
+    }
+
+    #[test]
+    fn key_low_bits_collision() {
+        let mut kv = Kv::with_hasher_and_store(IdentityHasher::default(), TestStore::new());
+        let a = 1u64;
+        let b = 1u64 | (1 << 32);
+
+        assert!(kv.insert(a, 10u32).is_ok());
+        assert!(kv.insert(b, 20u32).is_ok());
+        assert_eq!(kv.get::<u32>(a).unwrap(), Some(10));
+        assert_eq!(kv.get::<u32>(b).unwrap(), Some(20));
+
+        assert!(kv.forget(a).is_ok());
+        assert_eq!(kv.exists(a).unwrap(), false);
+        assert_eq!(kv.get::<u32>(b).unwrap(), Some(20));
+    }
+
+    #[test]
+    fn remove() {
+        let mut kv = new_kv();
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.insert("b", 2u8).is_ok());
+        assert!(kv.insert("c", 3u64).is_ok());
+        let size = kv.size().unwrap();
+
+        assert_eq!(kv.remove::<u32>("x").unwrap(), None);
+        assert!(kv.remove::<u64>("a").is_err());
+
+        assert_eq!(kv.remove::<u32>("a").unwrap(), Some(1));
+        assert_eq!(kv.size().unwrap(), size - META_SZ - 4);
+        assert_eq!(kv.amount().unwrap(), 2);
+        assert_eq!(kv.exists("a").unwrap(), false);
+        assert_eq!(kv.get::<u8>("b").unwrap(), Some(2));
+        assert_eq!(kv.get::<u64>("c").unwrap(), Some(3));
+
+        // Last entry
+        assert_eq!(kv.remove::<u64>("c").unwrap(), Some(3));
+        assert_eq!(kv.size().unwrap(), META_SZ + 1);
+        assert_eq!(kv.get::<u8>("b").unwrap(), Some(2));
+
+        assert_eq!(kv.remove::<u8>("b").unwrap(), Some(2));
+        assert_eq!(kv.size().unwrap(), 0);
+        assert_eq!(kv.amount().unwrap(), 0);
+
+        assert!(kv.insert("a", 4u16).is_ok());
+        assert_eq!(kv.get::<u16>("a").unwrap(), Some(4));
+    }
+
+    #[test]
+    fn compact() {
+        let mut kv = new_kv();
+        assert!(kv.compact().is_ok());
+        assert_eq!(kv.size().unwrap(), 0);
+
+        for (i, k) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+            assert!(kv.insert(k, i as u32).is_ok());
+        }
+        assert!(kv.forget("a").is_ok());
+        assert!(kv.forget("c").is_ok());
+        assert!(kv.forget("e").is_ok());
+        assert!(kv.compact().is_ok());
+        assert_eq!(kv.size().unwrap(), 2 * (META_SZ + 4));
+        assert_eq!(kv.amount().unwrap(), 2);
+        assert_eq!(kv.get::<u32>("b").unwrap(), Some(1));
+        assert_eq!(kv.get::<u32>("d").unwrap(), Some(3));
+        assert_eq!(kv.exists("a").unwrap(), false);
+
+        assert!(kv.forget("b").is_ok());
+        assert!(kv.forget("d").is_ok());
+        assert!(kv.compact().is_ok());
+        assert_eq!(kv.size().unwrap(), 0);
+        assert_eq!(kv.amount().unwrap(), 0);
+    }
+
+    #[test]
+    fn iter() {
+        let mut kv = new_kv();
+        assert_eq!(kv.iter().count(), 0);
+
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.insert("b", 2u8).is_ok());
+        assert!(kv.insert("c", 3u64).is_ok());
+        assert!(kv.forget("b").is_ok());
+
+        let entries = kv.iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(entries, [(kv.hash_key(&"a"), 4), (kv.hash_key(&"c"), 8)]);
+    }
+
+    #[test]
+    fn iter_typed() {
+        let mut kv = new_kv();
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.insert("b", 2u32).is_ok());
+        assert!(kv.insert("c", 3u32).is_ok());
+        assert!(kv.forget("b").is_ok());
+
+        let values = kv
+            .iter_typed::<u32>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(values, [(kv.hash_key(&"a"), 1), (kv.hash_key(&"c"), 3)]);
+
+        assert!(kv.insert("d", 4u8).is_ok());
+        assert!(kv
+            .iter_typed::<u32>()
+            .any(|v| matches!(v, Err(KvError::SizeMismatch))));
+    }
+
+    #[test]
+    fn len() {
+        let mut kv = new_kv();
+        assert_eq!(kv.len().unwrap(), 0);
+        assert_eq!(kv.is_empty().unwrap(), true);
+
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.insert("b", 2u32).is_ok());
+        assert_eq!(kv.len().unwrap(), 2);
+        assert_eq!(kv.is_empty().unwrap(), false);
+
+        assert!(kv.forget("a").is_ok());
+        assert_eq!(kv.len().unwrap(), 1);
+        assert_eq!(kv.amount().unwrap(), 2);
+
+        assert!(kv.forget("b").is_ok());
+        assert_eq!(kv.len().unwrap(), 0);
+        assert_eq!(kv.is_empty().unwrap(), true);
+    }
+
+    #[test]
+    fn get_ref() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Point {
+            x: u32,
+            y: u32,
+        }
+
+        let mut kv = new_kv();
+        assert!(kv.insert("p", Point { x: 1, y: 2 }).is_ok());
+        assert!(kv.insert("a", 1u8).is_ok());
+        assert!(kv.insert("q", Point { x: 3, y: 4 }).is_ok());
+
+        assert_eq!(
+            kv.get_ref::<Point>("p").unwrap(),
+            Some(&Point { x: 1, y: 2 })
+        );
+        assert_eq!(kv.get_ref::<Point>("x").unwrap(), None);
+        assert!(matches!(kv.get_ref::<u8>("p"), Err(KvError::SizeMismatch)));
+        assert!(matches!(kv.get_ref::<Point>("q"), Err(KvError::Misaligned)));
+
+        kv.get_mut::<Point>("p").unwrap().unwrap().y = 20;
+        #[cfg(not(feature = "crc"))]
+        assert_eq!(kv.get::<Point>("p").unwrap(), Some(Point { x: 1, y: 20 }));
+        #[cfg(feature = "crc")]
+        {
+            assert!(matches!(kv.get::<Point>("p"), Err(KvError::Corrupt)));
+            assert!(kv.update("p", Point { x: 1, y: 20 }).is_ok());
+            assert_eq!(
+                kv.get_ref::<Point>("p").unwrap(),
+                Some(&Point { x: 1, y: 20 })
+            );
+        }
+    }
+
+    #[test]
+    fn upsert() {
+        let mut kv = new_kv();
+        assert!(kv.upsert("a", 1u8).is_ok());
+        assert!(kv.insert("b", 2u16).is_ok());
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(1));
+
+        assert!(kv.upsert("a", 3u8).is_ok());
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(3));
+        assert_eq!(kv.amount().unwrap(), 2);
+
+        assert!(kv.upsert("a", 0xdead_beefu32).is_ok());
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(0xdead_beef));
+        assert!(kv.get::<u8>("a").is_err());
+        assert_eq!(kv.get::<u16>("b").unwrap(), Some(2));
+
+        assert!(kv.upsert("a", 5u8).is_ok());
+        assert_eq!(kv.get::<u8>("a").unwrap(), Some(5));
+        assert_eq!(kv.get::<u16>("b").unwrap(), Some(2));
+        assert_eq!(kv.amount().unwrap(), 2);
+        assert_eq!(kv.size().unwrap(), 2 * META_SZ + 3);
+    }
+
+    #[test]
+    fn bytes() {
+        let mut kv = new_kv();
+        let blob = (0..77u8).collect::<Vec<_>>();
+        assert!(kv.insert_bytes("blob", &blob).is_ok());
+        assert!(kv.insert_bytes("empty", &[]).is_ok());
+        assert!(kv.insert_bytes("blob", &[1]).is_err());
+
+        assert_eq!(kv.value_size("blob").unwrap(), Some(77));
+        assert_eq!(kv.value_size("empty").unwrap(), Some(0));
+        assert_eq!(kv.value_size("x").unwrap(), None);
+
+        let mut dst = [0u8; 100];
+        assert_eq!(kv.get_bytes_into("blob", &mut dst).unwrap(), Some(77));
+        assert_eq!(&dst[..77], &blob[..]);
+        assert_eq!(kv.get_bytes_into("empty", &mut dst).unwrap(), Some(0));
+        assert_eq!(kv.get_bytes_into("x", &mut dst).unwrap(), None);
+        assert!(matches!(
+            kv.get_bytes_into("blob", &mut dst[..76]),
+            Err(KvError::SizeMismatch)
+        ));
+    }
+
+    #[test]
+    fn entry() {
+        let mut kv = new_kv();
+        assert_eq!(kv.entry("a").unwrap().is_occupied(), false);
+        assert_eq!(kv.entry("a").unwrap().or_insert(1u32).unwrap(), 1);
+        assert_eq!(kv.entry("a").unwrap().is_occupied(), true);
+        assert_eq!(kv.entry("a").unwrap().or_insert(2u32).unwrap(), 1);
+        assert_eq!(kv.entry("b").unwrap().or_insert_with(|| 3u32).unwrap(), 3);
+        assert_eq!(
+            kv.entry("b")
+                .unwrap()
+                .or_insert_with::<u32, _>(|| unreachable!())
+                .unwrap(),
+            3
+        );
+
+        let v = kv
+            .entry("a")
+            .unwrap()
+            .and_modify(|v: &mut u32| *v += 10)
+            .unwrap()
+            .or_insert(0u32)
+            .unwrap();
+        assert_eq!(v, 11);
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(11));
+
+        let v = kv
+            .entry("c")
+            .unwrap()
+            .and_modify(|v: &mut u32| *v += 10)
+            .unwrap()
+            .or_insert(0u32)
+            .unwrap();
+        assert_eq!(v, 0);
+        assert!(kv.entry("a").unwrap().and_modify(|_: &mut u8| ()).is_err());
+    }
+
+    #[test]
+    fn byte_order() {
+        let mut le = Kv::with_hasher_and_store(IdentityHasher::default(), TestStore::new());
+        assert!(le.insert(1u64, 2u32).is_ok());
+        assert_eq!(&le.store().as_bytes()[8..12], &(META_SZ + 4).to_le_bytes());
+
+        let be = Kv::<u64, _, _, BigEndian>::with_byte_order(
+            IdentityHasher::default(),
+            le.store().clone(),
+        );
+        assert_ne!(be.size().unwrap(), le.size().unwrap());
+        assert_eq!(be.size().unwrap(), (META_SZ + 4).swap_bytes());
+
+        let mut be = Kv::<u64, _, _, BigEndian>::with_byte_order(
+            IdentityHasher::default(),
+            TestStore::new(),
+        );
+        assert!(be.insert(1u64, 2u32).is_ok());
+        assert_eq!(&be.store().as_bytes()[8..12], &(META_SZ + 4).to_be_bytes());
+        assert_eq!(be.size().unwrap(), META_SZ + 4);
+        assert_eq!(be.get::<u32>(1).unwrap(), Some(2));
+    }
+
+    #[cfg(feature = "type-tag")]
+    #[test]
+    fn type_tag() {
+        let mut kv = new_kv();
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(matches!(kv.get::<f32>("a"), Err(KvError::TypeMismatch)));
+        assert!(matches!(kv.update("a", 1f32), Err(KvError::TypeMismatch)));
+        assert!(matches!(kv.get::<[u8; 4]>("a"), Err(KvError::TypeMismatch)));
+        assert!(matches!(kv.get::<u8>("a"), Err(KvError::SizeMismatch)));
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(1));
+        assert_eq!(kv.contains_typed::<f32>("a").unwrap(), false);
+        assert_eq!(kv.contains_typed::<u32>("a").unwrap(), true);
+
+        assert!(kv.upsert("a", 2f32).is_ok());
+        assert_eq!(kv.get::<f32>("a").unwrap(), Some(2.0));
+        assert!(matches!(kv.get::<u32>("a"), Err(KvError::TypeMismatch)));
+
+        assert!(kv.insert_bytes("b", &[0; 4]).is_ok());
+        assert!(matches!(kv.get::<u32>("b"), Err(KvError::TypeMismatch)));
+    }
+
+    #[cfg(feature = "crc")]
+    #[test]
+    fn crc() {
+        let mut kv = new_kv();
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.insert("b", 2u32).is_ok());
+        assert!(kv.insert_bytes("c", &[1, 2, 3]).is_ok());
+        assert!(kv.update("b", 3u32).is_ok());
+        assert!(kv.verify().is_ok());
+        assert_eq!(kv.get::<u32>("b").unwrap(), Some(3));
+
+        let addr = kv.find(kv.hash_key(&"b")).unwrap().unwrap();
+        kv.store().as_bytes_mut()[(addr + META_SZ) as usize] ^= 0x10;
+        assert!(matches!(kv.get::<u32>("b"), Err(KvError::Corrupt)));
+        assert!(matches!(kv.verify(), Err(KvError::Corrupt)));
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(1));
+
+        // Forgotten values aren't checked
+        assert!(kv.forget("b").is_ok());
+        assert!(kv.verify().is_ok());
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let mut kv = new_kv();
+        let mut calls = 0;
+        let v = kv.get_or_insert_with("a", || {
+            calls += 1;
+            7u32
+        });
+        assert_eq!(v.unwrap(), 7);
+        assert_eq!(calls, 1);
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(7));
+
+        let v = kv.get_or_insert_with("a", || {
+            calls += 1;
+            8u32
+        });
+        assert_eq!(v.unwrap(), 7);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn index() {
+        let hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        let mut scan = Kv::with_hasher_and_store(hasher.clone(), TestStore::new());
+        for i in 0..500u32 {
+            assert!(scan.insert(i, i * 2).is_ok());
+        }
+        // Build the index from an existing store
+        let mut indexed = Kv::with_index(hasher, scan.store().clone()).unwrap();
+
+        for kv in [&mut scan, &mut indexed] {
+            for i in (0..500u32).step_by(3) {
+                assert!(kv.forget(i).is_ok());
+            }
+            for i in (1..500u32).step_by(7) {
+                let _ = kv.remove::<u32>(i);
+            }
+            assert!(kv.compact().is_ok());
+            for i in 500..600u32 {
+                assert!(kv.insert(i, i * 2).is_ok());
+            }
+            assert!(kv.upsert(2, 1u8).is_ok());
+        }
+
+        for i in 0..700u32 {
+            assert_eq!(scan.exists(i).unwrap(), indexed.exists(i).unwrap());
+            match i {
+                2 => assert_eq!(scan.get::<u8>(i).unwrap(), indexed.get::<u8>(i).unwrap()),
+                _ => assert_eq!(scan.get::<u32>(i).unwrap(), indexed.get::<u32>(i).unwrap()),
+            }
+        }
+        assert_eq!(indexed.len().unwrap(), scan.len().unwrap());
+
+        assert!(indexed.reset().is_ok());
+        assert_eq!(indexed.exists(10).unwrap(), false);
+    }
+
+    #[test]
+    fn open() {
+        type TestKv = Kv<&'static str, IdentityHasher, StaticDataStore<64>>;
+        let mut kv = TestKv::format(StaticDataStore::new()).unwrap();
+        assert!(kv.insert("a", 1u32).is_ok());
+
+        let mut kv = TestKv::open(kv.store().clone()).unwrap();
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(1));
+
+        assert!(matches!(
+            TestKv::open(StaticDataStore::new()),
+            Err(KvError::BadFormat)
+        ));
+        let mut store = StaticDataStore::<64>::new();
+        store.as_bytes_mut().fill(0xa5);
+        assert!(matches!(TestKv::open(store), Err(KvError::BadFormat)));
+
+        let mut store = kv.store().clone();
+        store.as_bytes_mut()[4] = VERSION + 1;
+        assert!(matches!(TestKv::open(store), Err(KvError::BadFormat)));
+
+        let mut store = kv.store().clone();
+        let size = 64 - HEADER_SZ;
+        store.as_bytes_mut()[8..12].copy_from_slice(&size.to_le_bytes());
+        assert!(TestKv::open(store.clone()).is_ok());
+        store.as_bytes_mut()[8..12].copy_from_slice(&(size + 1).to_le_bytes());
+        assert!(matches!(
+            TestKv::open(store.clone()),
+            Err(KvError::Truncated)
+        ));
+
+        // Nothing is recovered in a store that doesn't pass the checks
+        #[cfg(feature = "journal")]
+        {
+            store.as_bytes_mut()[JOURNAL_ADDR as usize..][..4]
+                .copy_from_slice(&FORGET.to_le_bytes());
+            let before = store.clone();
+            assert!(matches!(
+                Kv::<&'static str, IdentityHasher, _>::open(&mut store),
+                Err(KvError::Truncated)
+            ));
+            assert_eq!(store.as_bytes(), before.as_bytes());
+        }
+    }
+
+    #[test]
+    fn layout_mismatch() {
+        let mut kv =
+            Kv::<&'static str, IdentityHasher, StaticDataStore<64>>::format(StaticDataStore::new())
+                .unwrap();
+        let store = kv.store().clone();
+
+        assert!(matches!(
+            Kv::<&'static str, IdentityHasher, _, BigEndian>::open(store.clone()),
+            Err(KvError::LayoutMismatch)
+        ));
+        assert!(matches!(
+            Kv::<&'static str, IdentityHasher, _, LittleEndian, u32>::open(store.clone()),
+            Err(KvError::LayoutMismatch)
+        ));
+        for flag in [TYPE_TAG_FLAG, CRC_FLAG, JOURNAL_FLAG] {
+            let mut store = store.clone();
+            store.as_bytes_mut()[5] ^= flag;
+            assert!(matches!(
+                Kv::<&'static str, IdentityHasher, _>::open(store),
+                Err(KvError::LayoutMismatch)
+            ));
+        }
+
+        let mut kv = Kv::<&'static str, IdentityHasher, _, BigEndian, u16>::format(
+            StaticDataStore::<64>::new(),
+        )
+        .unwrap();
+        assert_eq!(kv.store().as_bytes()[5] & BIG_ENDIAN_FLAG, BIG_ENDIAN_FLAG);
+        assert_eq!(kv.store().as_bytes()[6], 2);
+        assert!(
+            Kv::<&'static str, IdentityHasher, _, BigEndian, u16>::open(kv.store().clone()).is_ok()
+        );
+    }
+
+    #[test]
+    fn space() {
+        let mut kv =
+            Kv::with_hasher_and_store(IdentityHasher::default(), StaticDataStore::<256>::new());
+        assert_eq!(kv.used_space().unwrap(), 0);
+        assert_eq!(kv.free_space().unwrap(), 256 - HEADER_SZ);
+        assert!(kv.insert(1u64, 2u32).is_ok());
+        assert!(kv.insert(2u64, 3u64).is_ok());
+        assert_eq!(kv.used_space().unwrap(), 2 * META_SZ + 12);
+        assert_eq!(kv.free_space().unwrap(), 256 - HEADER_SZ - 2 * META_SZ - 12);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn shrink_store() {
+        let mut kv =
+            Kv::with_hasher_and_store(RandomState::new().build_hasher(), HeapDataStore::new());
+        for i in 0..100u32 {
+            assert!(kv.insert(i, [i; 4]).is_ok());
+        }
+        for i in 10..100u32 {
+            assert!(kv.forget(i).is_ok());
+        }
+        let before = kv.store().len();
+        assert!(kv.shrink_store().is_ok());
+        let forgotten = kv.store().len();
+        assert_eq!(forgotten < before, true);
+        assert!(kv.compact().is_ok());
+        assert!(kv.shrink_store().is_ok());
+        assert_eq!(kv.store().len() < forgotten, true);
+        assert_eq!(kv.store().len(), (HEADER_SZ + kv.size().unwrap()) as usize);
+        for i in 0..10u32 {
+            assert_eq!(kv.get::<[u32; 4]>(i).unwrap(), Some([i; 4]));
+        }
+        assert!(kv.insert(100u32, [0u32; 4]).is_ok());
+
+        // Rounded to the growth step
+        let mut kv = Kv::<u32, IdentityHasher, _>::format(HeapDataStore::with_strategy(
+            0,
+            GrowthStrategy::Fixed(64),
+        ))
+        .unwrap();
+        assert_eq!(kv.store().len(), 64);
+        for i in 0..3 {
+            assert!(kv.insert(i, [0u8; 32]).is_ok());
+        }
+        assert_eq!(
+            kv.store().len() as u32,
+            (HEADER_SZ + 3 * (META_SZ + 32)).next_multiple_of(64)
+        );
+        assert!(kv.forget(0).is_ok());
+        assert!(kv.forget(1).is_ok());
+        assert!(kv.compact().is_ok());
+        assert!(kv.shrink_store().is_ok());
+        assert_eq!(
+            kv.store().len() as u32,
+            (HEADER_SZ + META_SZ + 32).next_multiple_of(64)
+        );
+        assert_eq!(kv.get::<[u8; 32]>(2).unwrap(), Some([0; 32]));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn from_bytes() {
+        let mut kv = Kv::with_hasher_and_store(IdentityHasher::default(), HeapDataStore::new());
+        assert!(kv.insert(1u64, 10u32).is_ok());
+        assert!(kv.insert(2u64, 20u16).is_ok());
+        let bytes = kv.store().clone().into_inner();
+
+        let mut kv = Kv::<u64, _, _>::with_hasher_and_store(
+            IdentityHasher::default(),
+            HeapDataStore::from_vec(bytes.clone()),
+        );
+        assert_eq!(kv.get::<u32>(1).unwrap(), Some(10));
+        assert_eq!(kv.get::<u16>(2).unwrap(), Some(20));
+
+        let mut array = [0u8; 128];
+        array.copy_from_slice(&bytes[..128]);
+        let mut kv = Kv::<u64, _, _>::with_hasher_and_store(
+            IdentityHasher::default(),
+            StaticDataStore::from_array(array),
+        );
+        assert_eq!(kv.get::<u32>(1).unwrap(), Some(10));
+        assert_eq!(kv.get::<u16>(2).unwrap(), Some(20));
+        assert_eq!(kv.store().clone().into_inner(), array);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn display_error() {
+        use std::{error::Error, io, string::ToString};
+
+        assert_eq!(
+            KvError::<io::Error>::Conflict.to_string(),
+            "key already exists"
+        );
+        assert_eq!(KvError::<io::Error>::NotFound.to_string(), "key not found");
+        assert_eq!(
+            KvError::<io::Error>::SizeMismatch.to_string(),
+            "value has a different size"
+        );
+        assert_eq!(
+            KvError::<io::Error>::Truncated.to_string(),
+            "store is smaller than its contents"
+        );
+        assert_eq!(
+            KvError::<io::Error>::ValueTooLarge.to_string(),
+            "value is larger than the maximum value size"
+        );
+        assert_eq!(KvError::<io::Error>::Corrupt.source().is_none(), true);
+
+        let e = KvError::Store(io::Error::other("disk on fire"));
+        assert_eq!(e.to_string(), "store error: disk on fire");
+        assert_eq!(e.source().unwrap().to_string(), "disk on fire");
+        assert_eq!(e.source().unwrap().is::<io::Error>(), true);
+    }
+
+    /// Reads and writes at most 3 bytes at a time
+    #[derive(Default)]
+    struct ShortStore(StaticDataStore<128>);
+
+    impl KvDataAccess for ShortStore {
+        type Error = DataStoreError;
+
+        fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+            let len = dst.len().min(3);
+            self.0.read(address, &mut dst[..len])
+        }
+
+        fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+            self.0.write(address, &data[..data.len().min(3)])
+        }
+    }
+
+    #[test]
+    fn short_access() {
+        let mut store = ShortStore::default();
+        assert!(store.write_exact(1, &[1, 2, 3, 4, 5, 6, 7]).is_ok());
+        let mut dst = [0u8; 8];
+        assert!(store.read_exact(0, &mut dst).is_ok());
+        assert_eq!(dst, [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!(matches!(
+            store.read_exact(124, &mut dst),
+            Err(DataStoreError::OutOfMemory)
+        ));
+
+        let mut kv = Kv::<u64, _, _>::with_hasher_and_store(IdentityHasher::default(), store);
+        assert!(kv.insert(1, [7u8; 17]).is_ok());
+        assert!(kv.insert(2, 0x0102_0304_0506_0708u64).is_ok());
+        assert_eq!(kv.get::<[u8; 17]>(1).unwrap(), Some([7; 17]));
+        assert_eq!(kv.get::<u64>(2).unwrap(), Some(0x0102_0304_0506_0708));
+    }
+
+    #[test]
+    fn flush() {
+        #[derive(Default)]
+        struct FlushCounter(StaticDataStore<64>, usize);
+
+        impl KvDataAccess for FlushCounter {
+            type Error = DataStoreError;
+
+            fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+                self.0.read(address, dst)
+            }
+
+            fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+                self.0.write(address, data)
+            }
+
+            fn flush(&mut self) -> Result<(), Self::Error> {
+                self.1 += 1;
+                Ok(())
+            }
+        }
+
+        let mut kv = Kv::<u64, _, _>::with_hasher_and_store(
+            IdentityHasher::default(),
+            Buffered::<_, 16>::new(FlushCounter::default()),
+        );
+        assert!(kv.insert(1, 2u32).is_ok());
+        assert_eq!(kv.get::<u32>(1).unwrap(), Some(2));
+        assert_eq!(kv.store().inner().1, 0);
+        assert!(kv.flush().is_ok());
+        assert_eq!(kv.store().inner().1, 1);
+        assert_eq!(
+            kv.store().inner().0.as_bytes()[HEADER_SZ as usize..][..8],
+            1u64.to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn key_width() {
+        fn check<B: ByteOrder, W: KeyWidth>() {
+            let mut kv =
+                Kv::<u64, _, _, B, W>::with_byte_order(IdentityHasher::default(), TestStore::new());
+            for i in 0..50u64 {
+                assert!(kv.insert(i, i as u32).is_ok());
+            }
+            // The tombstone can't be a key
+            assert!(kv.insert(W::TOMBSTONE, 1u8).is_ok());
+            assert_eq!(
+                kv.used_space().unwrap(),
+                50 * (W::SIZE + META_SZ - 8 + 4) + W::SIZE + META_SZ - 8 + 1
+            );
+            for i in (0..50u64).step_by(2) {
+                assert!(kv.forget(i).is_ok());
+            }
+            assert!(kv.compact().is_ok());
+            for i in 0..50u64 {
+                assert_eq!(kv.get::<u32>(i).unwrap(), (i % 2 == 1).then_some(i as u32));
+            }
+            assert_eq!(kv.get::<u8>(W::TOMBSTONE).unwrap(), Some(1));
+            assert_eq!(kv.len().unwrap(), 26);
+        }
+        check::<LittleEndian, u16>();
+        check::<BigEndian, u16>();
+        check::<LittleEndian, u32>();
+        check::<BigEndian, u32>();
+        check::<LittleEndian, u64>();
+        check::<BigEndian, u64>();
+
+        // Narrower keys collide sooner
+        let mut kv = Kv::<u64, _, _, LittleEndian, u16>::with_byte_order(
+            IdentityHasher::default(),
+            TestStore::new(),
+        );
+        assert!(kv.insert(1, 1u8).is_ok());
+        assert!(matches!(kv.insert(0x1_0001, 1u8), Err(KvError::Conflict)));
+        assert_eq!(
+            &kv.store().as_bytes()[HEADER_SZ as usize..][..3],
+            &[1, 0, 1]
+        );
+    }
+
+    #[test]
+    fn replace() {
+        let mut kv = new_kv();
+        assert_eq!(kv.replace("a", 1u32).unwrap(), None);
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(1));
+        assert_eq!(kv.replace("a", 2u32).unwrap(), Some(1));
+        assert_eq!(kv.replace("a", 3u32).unwrap(), Some(2));
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(3));
+        assert!(matches!(kv.replace("a", 4u8), Err(KvError::SizeMismatch)));
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(3));
+        assert_eq!(kv.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn modify() {
+        let mut kv = new_kv();
+        assert_eq!(kv.modify("count", |c: &mut u32| *c += 1).unwrap(), false);
+
+        assert!(kv.insert("count", 0u32).is_ok());
+        for _ in 0..3 {
+            assert_eq!(kv.modify("count", |c: &mut u32| *c += 1).unwrap(), true);
+        }
+        assert_eq!(kv.get::<u32>("count").unwrap(), Some(3));
+
+        assert!(matches!(
+            kv.modify("count", |c: &mut u8| *c += 1),
+            Err(KvError::SizeMismatch)
+        ));
+        assert_eq!(kv.get::<u32>("count").unwrap(), Some(3));
+    }
+
+    #[test]
+    fn max_value_size() {
+        let mut kv =
+            Kv::<&'static str, IdentityHasher, TestStore>::format(TestStore::new()).unwrap();
+        kv.set_max_value_size(4);
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.insert("b", 2u16).is_ok());
+        assert!(matches!(kv.insert("c", 3u64), Err(KvError::ValueTooLarge)));
+        assert!(matches!(kv.upsert("a", 4u64), Err(KvError::ValueTooLarge)));
+        assert!(matches!(
+            kv.insert_bytes("d", &[0; 5]),
+            Err(KvError::ValueTooLarge)
+        ));
+
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(1));
+        assert_eq!(kv.exists("c").unwrap(), false);
+        assert_eq!(kv.len().unwrap(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn reserve() {
+        let mut kv = Kv::with_hasher_and_store(
+            IdentityHasher::default(),
+            HeapDataStore::with_capacity(HEADER_SZ as usize),
+        );
+        assert!(kv.insert("a", 1u32).is_ok());
+        let used = (HEADER_SZ + kv.size().unwrap()) as usize;
+
+        assert!(kv.reserve(1000).is_ok());
+        assert_eq!(kv.store().len(), used + 1000);
+        assert!(kv.reserve(10).is_ok());
+        assert_eq!(kv.store().len(), used + 1000);
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(1));
+    }
+
+    #[test]
+    fn contains_typed() {
+        let mut kv = new_kv();
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert_eq!(kv.contains_typed::<u32>("a").unwrap(), true);
+        assert_eq!(kv.contains_typed::<u64>("a").unwrap(), false);
+        assert_eq!(kv.contains_typed::<u32>("b").unwrap(), false);
+    }
+
+    #[test]
+    fn merge_from() {
+        let hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        let mut a = Kv::with_hasher_and_store(hasher.clone(), TestStore::new());
+        assert!(a.insert("a", 1u32).is_ok());
+        assert!(a.insert("b", 2u32).is_ok());
+
+        let mut b = Kv::with_hasher_and_store(hasher, StaticDataStore::<256>::new());
+        assert!(b.insert("b", 20u8).is_ok());
+        assert!(b.insert("c", 30u64).is_ok());
+        assert!(b.insert_bytes("d", &[4; 40]).is_ok());
+        assert!(b.insert("e", 50u32).is_ok());
+        assert!(b.forget("e").is_ok());
+
+        let mut skip = a.clone();
+        assert_eq!(skip.merge_from(&b, MergePolicy::Skip).unwrap(), 2);
+        assert_eq!(skip.get::<u32>("b").unwrap(), Some(2));
+        assert_eq!(skip.get::<u64>("c").unwrap(), Some(30));
+        let mut buf = [0u8; 40];
+        assert_eq!(skip.get_bytes_into("d", &mut buf).unwrap(), Some(40));
+        assert_eq!(buf, [4; 40]);
+        assert_eq!(skip.exists("e").unwrap(), false);
+        assert_eq!(skip.len().unwrap(), 4);
+        assert!(skip.verify().is_ok());
+
+        // "b" is overwritten, but only "c" and "d" are counted
+        let mut overwrite = a.clone();
+        assert_eq!(overwrite.merge_from(&b, MergePolicy::Overwrite).unwrap(), 2);
+        assert_eq!(overwrite.get::<u32>("a").unwrap(), Some(1));
+        assert_eq!(overwrite.get::<u8>("b").unwrap(), Some(20));
+        assert_eq!(overwrite.get::<u64>("c").unwrap(), Some(30));
+        assert_eq!(overwrite.len().unwrap(), 4);
+    }
+
+    #[test]
+    fn merge_from_other_store() {
+        // A `SubStoreError<DataStoreError>` store merging from a `DataStoreError` one
+        let mut src = Kv::with_hasher_and_store(IdentityHasher::default(), TestStore::new());
+        assert!(src.insert("a", 1u32).is_ok());
+        assert!(src.insert("b", 2u16).is_ok());
+
+        let mut dst = Kv::with_hasher_and_store(
+            IdentityHasher::default(),
+            SubStore::new(StaticDataStore::<256>::new(), 64, 128),
+        );
+        assert!(dst.insert("b", 20u8).is_ok());
+        assert_eq!(dst.merge_from(&src, MergePolicy::Skip).unwrap(), 1);
+        assert_eq!(dst.get::<u32>("a").unwrap(), Some(1));
+        assert_eq!(dst.get::<u8>("b").unwrap(), Some(20));
+
+        // Errors from the other store are converted
+        let mut src = src.clone();
+        src.store().as_bytes_mut()[AMOUNT_ADDR as usize..][..4].fill(0xff);
+        assert!(matches!(
+            dst.merge_from(&src, MergePolicy::Overwrite),
+            Err(KvError::Store(SubStoreError::Store(
+                DataStoreError::OutOfMemory
+            )))
+        ));
+    }
+
+    #[test]
+    fn reuse_freed() {
+        let mut kv = Kv::<&'static str, IdentityHasher, TestStore>::with_freelist(TestStore::new());
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.insert("b", 2u32).is_ok());
+        assert!(kv.forget("a").is_ok());
+
+        let size = kv.size().unwrap();
+        assert!(kv.insert("c", 3u32).is_ok());
+        assert_eq!(kv.size().unwrap(), size);
+        assert_eq!(kv.amount().unwrap(), 2);
+        assert_eq!(kv.get::<u32>("b").unwrap(), Some(2));
+        assert_eq!(kv.get::<u32>("c").unwrap(), Some(3));
+        assert_eq!(kv.exists("a").unwrap(), false);
+        assert!(kv.verify().is_ok());
+
+        // No slot of the same size
+        assert!(kv.forget("b").is_ok());
+        assert!(kv.insert("d", 4u64).is_ok());
+        assert_eq!(kv.size().unwrap(), size + META_SZ + 8);
+        assert_eq!(kv.len().unwrap(), 2);
+
+        let mut kv = Kv::with_hasher_and_store(IdentityHasher::default(), TestStore::new());
+        kv.set_reuse_freed(true);
+        #[cfg(feature = "alloc")]
+        assert!(kv.enable_index().is_ok());
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.forget("a").is_ok());
+        assert!(kv.insert("a", 5u32).is_ok());
+        assert_eq!(kv.amount().unwrap(), 1);
+        assert_eq!(kv.get::<u32>("a").unwrap(), Some(5));
+    }
+
+    #[test]
+    fn secure_reset() {
+        let mut kv = Kv::with_hasher_and_store(IdentityHasher::default(), TestStore::new());
+        assert!(kv.insert("a", u32::MAX).is_ok());
+        assert!(kv.insert_bytes("b", &[0xff; 40]).is_ok());
+        let end = (HEADER_SZ + kv.size().unwrap()) as usize;
+
+        assert!(kv.secure_reset().is_ok());
+        assert_eq!(kv.len().unwrap(), 0);
+        assert_eq!(
+            kv.store().as_bytes()[HEADER_SZ as usize..end]
+                .iter()
+                .all(|&b| b == 0),
+            true
+        );
+    }
+
+    #[test]
+    fn export_import() {
+        let mut kv =
+            Kv::<&'static str, IdentityHasher, TestStore>::format(TestStore::new()).unwrap();
+        assert!(kv.insert("a", 1u32).is_ok());
+        assert!(kv.insert("b", 2u8).is_ok());
+        assert!(kv.insert_bytes("c", b"hello").is_ok());
+        assert!(kv.forget("b").is_ok());
+
+        let mut image = [0u8; 128];
+        assert!(matches!(
+            kv.export_into(&mut image[..HEADER_SZ as usize]),
+            Err(KvError::SizeMismatch)
+        ));
+        let len = kv.export_into(&mut image).unwrap();
+        assert_eq!(len, (HEADER_SZ + kv.size().unwrap()) as usize);
+
+        let mut copy =
+            Kv::<&'static str, IdentityHasher, TestStore>::format(TestStore::new()).unwrap();
+        #[cfg(feature = "alloc")]
+        assert!(copy.enable_index().is_ok());
+        assert!(copy.insert("d", 4u64).is_ok());
+        assert!(matches!(
+            copy.import_from(&image[..len - 1]),
+            Err(KvError::BadFormat)
+        ));
+        let mut bad = image;
+        bad[0] ^= 0xff;
+        assert!(matches!(
+            copy.import_from(&bad[..len]),
+            Err(KvError::BadFormat)
+        ));
+        bad = image;
+        bad[5] ^= CRC_FLAG;
+        assert!(matches!(
+            copy.import_from(&bad[..len]),
+            Err(KvError::LayoutMismatch)
+        ));
+        assert_eq!(copy.get::<u64>("d").unwrap(), Some(4));
+
+        let mut small = Kv::<&'static str, IdentityHasher, _>::format(StaticDataStore::<
+            { HEADER_SZ as usize + 32 },
+        >::new())
+        .unwrap();
+        assert!(small.insert("d", 4u8).is_ok());
+        assert!(matches!(
+            small.import_from(&image[..len]),
+            Err(KvError::Truncated)
+        ));
+        assert_eq!(small.get::<u8>("d").unwrap(), Some(4));
+
+        assert!(copy.import_from(&image[..len]).is_ok());
+
+        assert_eq!(copy.get::<u32>("a").unwrap(), kv.get::<u32>("a").unwrap());
+        assert_eq!(copy.get::<u8>("b").unwrap(), None);
+        let mut buf = [0u8; 8];
+        assert_eq!(copy.get_bytes_into("c", &mut buf).unwrap(), Some(5));
+        assert_eq!(&buf[..5], b"hello");
+        assert_eq!(copy.exists("d").unwrap(), false);
+        assert_eq!(copy.len().unwrap(), kv.len().unwrap());
+    }
+
+    #[cfg(feature = "journal")]
+    #[test]
+    fn recover() {
+        /// Fails after a number of writes, writing only half of the last one
+        #[derive(Default)]
+        struct CrashStore {
+            store: StaticDataStore<512>,
+            writes_left: Option<usize>,
+        }
+
+        impl KvDataAccess for CrashStore {
+            type Error = DataStoreError;
+
+            fn read(&self, address: u32, dst: &mut [u8]) -> Result<usize, Self::Error> {
+                self.store.read(address, dst)
+            }
+
+            fn write(&mut self, address: u32, data: &[u8]) -> Result<usize, Self::Error> {
+                match &mut self.writes_left {
+                    Some(0) => {
+                        self.store.write(address, &data[..data.len() / 2])?;
+                        Err(DataStoreError::OutOfMemory)
+                    }
+                    Some(n) => {
+                        *n -= 1;
+                        self.store.write(address, data)
+                    }
+                    None => self.store.write(address, data),
+                }
+            }
+        }
+
+        type CrashKv = Kv<&'static str, std::hash::DefaultHasher, CrashStore>;
+
+        /// Run `op` crashing after `n` writes, returning the recovered store and if `op` finished
+        fn crash(n: usize, op: impl FnOnce(&mut CrashKv) -> bool) -> (CrashKv, bool) {
+            let mut kv = CrashKv::format(CrashStore::default()).unwrap();
+            assert!(kv.insert("a", [1u8; 20]).is_ok());
+            assert!(kv.insert("b", 2u32).is_ok());
+            assert!(kv.insert("c", [3u8; 40]).is_ok());
+            kv.store().writes_left = Some(n);
+            let done = op(&mut kv);
+            let mut store = mem::take(kv.store());
+            store.writes_left = None;
+            let kv = CrashKv::open(store).unwrap();
+            assert_eq!(kv.iter().count() as u32, kv.len().unwrap());
+            assert!(kv.verify().is_ok());
+            (kv, done)
+        }
+
+        let mut n = 0;
+        loop {
+            let (mut kv, done) = crash(n, |kv| kv.insert("d", [4u8; 10]).is_ok());
+            let d = kv.get::<[u8; 10]>("d").unwrap();
+            assert_eq!(d == Some([4; 10]) || (!done && d.is_none()), true);
+            assert_eq!(kv.get::<[u8; 20]>("a").unwrap(), Some([1; 20]));
+            assert_eq!(kv.get::<u32>("b").unwrap(), Some(2));
+            assert_eq!(kv.get::<[u8; 40]>("c").unwrap(), Some([3; 40]));
+            n += 1;
+            if done {
+                break;
+            }
+        }
+
+        let mut n = 0;
+        loop {
+            let (mut kv, done) = crash(n, |kv| kv.forget("b").is_ok());
+            let b = kv.get::<u32>("b").unwrap();
+            assert_eq!(b.is_none() || (!done && b == Some(2)), true);
+            assert_eq!(kv.get::<[u8; 20]>("a").unwrap(), Some([1; 20]));
+            assert_eq!(kv.get::<[u8; 40]>("c").unwrap(), Some([3; 40]));
+            n += 1;
+            if done {
+                break;
+            }
+        }
+
+        let mut n = 0;
+        loop {
+            let (mut kv, done) = crash(n, |kv| kv.remove::<[u8; 20]>("a").is_ok());
+            let a = kv.get::<[u8; 20]>("a").unwrap();
+            assert_eq!(a.is_none() || (!done && a == Some([1; 20])), true);
+            assert_eq!(kv.get::<u32>("b").unwrap(), Some(2));
+            assert_eq!(kv.get::<[u8; 40]>("c").unwrap(), Some([3; 40]));
+            assert!(kv.insert("e", 5u16).is_ok());
+            assert_eq!(kv.get::<u16>("e").unwrap(), Some(5));
+            n += 1;
+            if done {
+                break;
+            }
+        }
+
+        // Reusing the slot of "b", the last write of an interrupted reuse is half of the key
+        let mut n = 0;
+        loop {
+            let (mut kv, done) = crash(n, |kv| {
+                kv.store().writes_left = None;
+                assert!(kv.forget("b").is_ok());
+                kv.set_reuse_freed(true);
+                kv.store().writes_left = Some(n);
+                kv.insert("e", 5u32).is_ok()
+            });
+            let e = kv.get::<u32>("e").unwrap();
+            assert_eq!(e == Some(5) || (!done && e.is_none()), true);
+            assert_eq!(kv.get::<u32>("b").unwrap(), None);
+            assert_eq!(kv.amount().unwrap(), 3);
+            assert_eq!(kv.len().unwrap(), 2 + e.is_some() as u32);
+            assert_eq!(kv.get::<[u8; 20]>("a").unwrap(), Some([1; 20]));
+            assert_eq!(kv.get::<[u8; 40]>("c").unwrap(), Some([3; 40]));
+            n += 1;
+            if done {
+                break;
+            }
+        }
+    }
 
     #[test]
     fn kv() {
-        let mut kv = Kv::new();
+        let mut kv = new_kv();
 
         // Double insert
         assert!(kv.insert("a", 42i32).is_ok());
@@ -1,16 +1,16 @@
 #![cfg_attr(all(not(test), not(feature = "std")), no_std)]
-#![feature(
-    maybe_uninit_uninit_array,
-    maybe_uninit_array_assume_init,
-    const_fn_trait_bound
-)]
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "std")]
+mod channel;
 mod error;
 mod kv;
 mod queue;
 mod stack;
 
+#[cfg(feature = "std")]
+pub use channel::*;
 pub use {error::*, kv::*, queue::*, stack::*};
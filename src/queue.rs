@@ -1,26 +1,57 @@
 use {
     crate::Error,
     core::{
+        cell::UnsafeCell,
         fmt,
         mem::{self, MaybeUninit},
+        ptr,
     },
 };
 
+/// A `Queue` with room for 16 elements
+pub type SmallQueue<T> = Queue<T, 16>;
+
 pub struct Queue<T, const N: usize> {
     buf: [MaybeUninit<T>; N],
     size: usize,
     r: usize,
     w: usize,
+    /// Largest size the queue has reached
+    #[cfg(feature = "stats")]
+    max_seen: usize,
 }
 
 impl<T, const N: usize> Queue<T, N> {
+    pub const CAPACITY: usize = N;
+
     pub const fn new() -> Self {
         Self {
-            buf: MaybeUninit::uninit_array::<N>(),
+            buf: [const { MaybeUninit::uninit() }; N],
             size: 0,
             r: 0,
             w: 0,
+            #[cfg(feature = "stats")]
+            max_seen: 0,
+        }
+    }
+
+    /// Build a queue from an iterator. Returns `Err(Error::Full)` if the iterator
+    /// yields more than `N` items, dropping the items consumed so far.
+    pub fn try_from_iter<I: IntoIterator<Item = T>>(iter: I) -> Result<Self, Error> {
+        let mut queue = Self::new();
+        for item in iter {
+            queue.push(item)?;
         }
+        Ok(queue)
+    }
+
+    /// Build a queue from an iterator using `push_overwrite`, so the last `N` items are kept.
+    pub fn from_iter_overwriting<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        for item in iter {
+            queue.push_overwrite(item);
+        }
+        queue
     }
 
     pub fn push(&mut self, item: T) -> Result<(), Error> {
@@ -59,6 +90,64 @@ impl<T, const N: usize> Queue<T, N> {
         }
     }
 
+    /// Like `pop`, but returns `Err(Error::Empty)` instead of `None`.
+    pub fn try_pop(&mut self) -> Result<T, Error> {
+        self.pop().ok_or(Error::Empty)
+    }
+
+    /// Copy as many elements from `src` as fit into the queue, returning how many were
+    /// written. Returns `Err(Error::Full)` if `src` isn't empty but nothing fits.
+    pub fn push_slice(&mut self, src: &[T]) -> Result<usize, Error>
+    where
+        T: Copy,
+    {
+        let count = src.len().min(N - self.size);
+        if count == 0 {
+            return match src.is_empty() {
+                true => Ok(0),
+                false => Err(Error::Full),
+            };
+        }
+        let first = count.min(N - self.w);
+        // SAFETY: buf[w..w + first] and buf[0..count - first] are free slots. T is Copy,
+        // so copying the bits is enough
+        unsafe {
+            let dst = self.buf.as_mut_ptr() as *mut T;
+            ptr::copy_nonoverlapping(src.as_ptr(), dst.add(self.w), first);
+            ptr::copy_nonoverlapping(src.as_ptr().add(first), dst, count - first);
+        }
+        self.size += count;
+        self.w = (self.w + count) % N;
+        #[cfg(feature = "stats")]
+        self.record_size();
+        Ok(count)
+    }
+
+    /// Push an item to the front of the queue, so it's the next one to be popped.
+    pub fn push_front(&mut self, item: T) -> Result<(), Error> {
+        match self.is_full() {
+            true => Err(Error::Full),
+            false => {
+                self.inc_size();
+                self.r = self.prev_r();
+                self.buf[self.r].write(item);
+                Ok(())
+            }
+        }
+    }
+
+    /// Pop the most recently pushed item from the back of the queue.
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            self.dec_size();
+            self.w = self.prev_w();
+            let v = mem::replace(&mut self.buf[self.w], MaybeUninit::uninit());
+            Some(unsafe { v.assume_init() })
+        }
+    }
+
     pub fn peek(&self) -> Option<&T> {
         match self.is_empty() {
             true => None,
@@ -66,6 +155,126 @@ impl<T, const N: usize> Queue<T, N> {
         }
     }
 
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        match self.is_empty() {
+            true => None,
+            false => Some(unsafe { self.buf[self.r].assume_init_mut() }),
+        }
+    }
+
+    /// Peek at the most recently pushed element.
+    pub fn peek_back(&self) -> Option<&T> {
+        match self.is_empty() {
+            true => None,
+            false => Some(unsafe { self.buf[self.prev_w()].assume_init_ref() }),
+        }
+    }
+
+    /// Get the element at `index`, where index 0 is the front of the queue.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        match index < self.size {
+            true => Some(unsafe { self.buf[(self.r + index) % N].assume_init_ref() }),
+            false => None,
+        }
+    }
+
+    pub fn contains(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.iter().any(|v| v == value)
+    }
+
+    /// Iterate over the elements in pop order, front to back.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &T> + DoubleEndedIterator {
+        (0..self.size).map(move |i| {
+            // SAFETY: The size elements starting at r are initialized
+            unsafe { self.buf[(self.r + i) % N].assume_init_ref() }
+        })
+    }
+
+    /// Returns the elements in pop order as two slices. The second slice is empty
+    /// unless the elements wrap around the end of the buffer.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (front, back) = match self.r + self.size <= N {
+            true => (&self.buf[self.r..self.r + self.size], &self.buf[0..0]),
+            false => (&self.buf[self.r..N], &self.buf[0..self.r + self.size - N]),
+        };
+        // SAFETY: Both ranges are within the size elements starting at r, which are initialized
+        unsafe {
+            (
+                mem::transmute::<&[MaybeUninit<T>], &[T]>(front),
+                mem::transmute::<&[MaybeUninit<T>], &[T]>(back),
+            )
+        }
+    }
+
+    /// Returns the elements in pop order as a single slice, or `None` if they wrap around
+    /// the end of the buffer.
+    pub fn as_contiguous_slice(&self) -> Option<&[T]> {
+        match self.as_slices() {
+            (front, []) => Some(front),
+            _ => None,
+        }
+    }
+
+    /// Rotate the buffer so the front of the queue is at index 0, and return all
+    /// elements as a single slice in pop order.
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        self.buf.rotate_left(self.r);
+        self.r = 0;
+        self.w = match self.size == N {
+            true => 0,
+            false => self.size,
+        };
+        // SAFETY: buf[0..size] is initialized after the rotation
+        unsafe { mem::transmute::<&mut [MaybeUninit<T>], &mut [T]>(&mut self.buf[0..self.size]) }
+    }
+
+    /// Drop all elements for which `pred` returns `true`, keeping the order of the rest.
+    pub fn drain_filter<F: FnMut(&T) -> bool>(&mut self, mut pred: F) {
+        let len = self.size;
+        // Elements not yet visited are leaked rather than double dropped if `pred` panics
+        self.size = 0;
+        for i in 0..len {
+            let idx = (self.r + i) % N;
+            // SAFETY: The len elements starting at r are initialized, and buf[idx] hasn't been
+            // moved or dropped yet
+            match pred(unsafe { self.buf[idx].assume_init_ref() }) {
+                // SAFETY: See above
+                true => unsafe { self.buf[idx].assume_init_drop() },
+                false => {
+                    self.buf.swap((self.r + self.size) % N, idx);
+                    self.size += 1;
+                }
+            }
+        }
+        self.w = (self.r + self.size) % N;
+    }
+
+    /// Split the queue into a [`Producer`] and a [`Consumer`] half that can be held at the
+    /// same time. Both halves are `!Send` and `!Sync`, so they're for single threaded use only,
+    /// e.g. passing the producer to one part of a program and the consumer to another.
+    /// Only the consumer removes elements, so unlike `Queue::push_overwrite` the producer can't
+    /// overwrite the front element of a full queue.
+    pub fn split(&mut self) -> (Producer<'_, T, N>, Consumer<'_, T, N>) {
+        let queue = UnsafeCell::from_mut(self);
+        (Producer { queue }, Consumer { queue })
+    }
+
+    /// Drop all elements, leaving the queue empty.
+    pub fn clear(&mut self) {
+        let (r, size) = (self.r, self.size);
+        // Reset before dropping so a panicking destructor can't cause a double drop
+        self.size = 0;
+        self.r = 0;
+        self.w = 0;
+        for i in 0..size {
+            // SAFETY: The size elements starting at r are initialized
+            unsafe { self.buf[(r + i) % N].assume_init_drop() };
+        }
+    }
+
     pub const fn capacity(&self) -> usize {
         N
     }
@@ -78,6 +287,19 @@ impl<T, const N: usize> Queue<T, N> {
         self.size == N
     }
 
+    /// Largest number of elements the queue has held since it was created or
+    /// `reset_high_water_mark` was called.
+    #[cfg(feature = "stats")]
+    pub const fn high_water_mark(&self) -> usize {
+        self.max_seen
+    }
+
+    /// Reset the high water mark to the current size.
+    #[cfg(feature = "stats")]
+    pub fn reset_high_water_mark(&mut self) {
+        self.max_seen = self.size;
+    }
+
     pub const fn is_empty(&self) -> bool {
         self.size == 0
     }
@@ -90,12 +312,27 @@ impl<T, const N: usize> Queue<T, N> {
         (self.r + 1) % N
     }
 
+    const fn prev_w(&self) -> usize {
+        (self.w + N - 1) % N
+    }
+
+    const fn prev_r(&self) -> usize {
+        (self.r + N - 1) % N
+    }
+
     fn inc_size(&mut self) {
         if self.size < N {
             self.size += 1;
+            #[cfg(feature = "stats")]
+            self.record_size();
         }
     }
 
+    #[cfg(feature = "stats")]
+    fn record_size(&mut self) {
+        self.max_seen = self.max_seen.max(self.size);
+    }
+
     fn dec_size(&mut self) {
         if self.size > 0 {
             self.size -= 1;
@@ -103,39 +340,170 @@ impl<T, const N: usize> Queue<T, N> {
     }
 }
 
-// TODO: Nice debug output for initialized values
+impl<T, const N: usize> Default for Queue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for Queue<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
 impl<T: fmt::Debug, const N: usize> fmt::Debug for Queue<T, N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Queue")
-            .field("buf", &self.buf)
-            .field("size", &self.size)
-            .field("r", &self.r)
-            .field("w", &self.w)
-            .finish()
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<T: defmt::Format, const N: usize> defmt::Format for Queue<T, N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "[");
+        for (i, v) in self.iter().enumerate() {
+            match i {
+                0 => defmt::write!(f, "{}", v),
+                _ => defmt::write!(f, ", {}", v),
+            }
+        }
+        defmt::write!(f, "]")
     }
 }
 
-impl<T: Copy, const N: usize> Clone for Queue<T, N> {
+impl<T: Clone, const N: usize> Clone for Queue<T, N> {
     fn clone(&self) -> Self {
-        Self {
-            buf: self.buf,
-            size: self.size,
+        let mut new = Self {
+            buf: [const { MaybeUninit::uninit() }; N],
+            size: 0,
             r: self.r,
-            w: self.w,
+            w: self.r,
+            #[cfg(feature = "stats")]
+            max_seen: self.max_seen,
+        };
+
+        // size is only raised after each element is written, so if a clone panics
+        // only the elements cloned so far are dropped
+        for item in self.iter() {
+            new.buf[new.w].write(item.clone());
+            new.size += 1;
+            new.w = new.next_w();
+        }
+
+        new
+    }
+}
+
+/// Producing half of a [`Queue`], created with [`Queue::split`].
+pub struct Producer<'a, T, const N: usize> {
+    queue: &'a UnsafeCell<Queue<T, N>>,
+}
+
+impl<T, const N: usize> Producer<'_, T, N> {
+    pub fn push(&mut self, item: T) -> Result<(), Error> {
+        let q = self.queue.get();
+        // SAFETY: The halves are !Send and !Sync, so nothing else accesses the queue during the
+        // call. The consumer may hold a reference to the front element from `peek`, so only the
+        // fields and the free slot at w are accessed, never the queue as a whole. w is only the
+        // front slot if the queue is empty, in which case `peek` handed out no reference.
+        unsafe {
+            if (*q).size == N {
+                return Err(Error::Full);
+            }
+            let w = (*q).w;
+            (*q).buf[w].write(item);
+            (*q).w = (w + 1) % N;
+            (*q).size += 1;
+            #[cfg(feature = "stats")]
+            {
+                (*q).max_seen = (*q).max_seen.max((*q).size);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Consuming half of a [`Queue`], created with [`Queue::split`].
+pub struct Consumer<'a, T, const N: usize> {
+    queue: &'a UnsafeCell<Queue<T, N>>,
+}
+
+impl<T, const N: usize> Consumer<'_, T, N> {
+    pub fn pop(&mut self) -> Option<T> {
+        // SAFETY: The halves are !Send and !Sync, and the producer never holds a reference into
+        // the queue between calls. Taking &mut self ends any reference handed out by `peek`.
+        unsafe { (*self.queue.get()).pop() }
+    }
+
+    /// Peek at the front element. The reference stays valid while the producer pushes, since
+    /// only the consumer removes elements.
+    pub fn peek(&self) -> Option<&T> {
+        let q = self.queue.get();
+        // SAFETY: See pop. Only the front slot is borrowed, which `Producer::push` doesn't touch
+        // while the queue isn't empty.
+        unsafe {
+            match (*q).size == 0 {
+                true => None,
+                false => Some((*q).buf[(*q).r].assume_init_ref()),
+            }
         }
     }
 }
 
-// TODO:
-// impl<T: Clone, const N: usize> Clone for Queue<T, N> {
-//     fn clone(&self) -> Self {
-//         todo!()
-//     }
-// }
+impl<T: PartialEq, const N: usize> PartialEq for Queue<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for Queue<T, N> {}
+
+/// Owning iterator over the elements of a [`Queue`] in pop order.
+pub struct IntoQueueIter<T, const N: usize> {
+    queue: Queue<T, N>,
+}
+
+impl<T, const N: usize> Iterator for IntoQueueIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.queue.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.queue.size(), Some(self.queue.size()))
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoQueueIter<T, N> {}
+
+impl<T, const N: usize> Drop for IntoQueueIter<T, N> {
+    fn drop(&mut self) {
+        while self.queue.pop().is_some() {}
+    }
+}
+
+impl<T, const N: usize> IntoIterator for Queue<T, N> {
+    type Item = T;
+    type IntoIter = IntoQueueIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoQueueIter { queue: self }
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, core::cell::Cell};
+
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
 
     #[test]
     fn clone() {
@@ -160,6 +528,478 @@ mod tests {
         assert_eq!(q1.is_empty(), false);
     }
 
+    #[test]
+    fn capacity_const() {
+        const CAPACITY: usize = Queue::<i32, 3>::CAPACITY;
+        let array = [0u8; Queue::<i32, 3>::CAPACITY];
+        assert_eq!(CAPACITY, 3);
+        assert_eq!(array.len(), Queue::<i32, 3>::new().capacity());
+    }
+
+    #[test]
+    fn clone_panic() {
+        struct PanicOnClone<'a>(bool, &'a Cell<usize>);
+
+        impl Clone for PanicOnClone<'_> {
+            fn clone(&self) -> Self {
+                assert!(!self.0, "clone failed");
+                Self(self.0, self.1)
+            }
+        }
+
+        impl Drop for PanicOnClone<'_> {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let mut q = Queue::<_, 3>::new();
+        assert!(q.push(PanicOnClone(false, &drops)).is_ok());
+        assert!(q.pop().is_some());
+        assert!(q.push(PanicOnClone(false, &drops)).is_ok());
+        assert!(q.push(PanicOnClone(false, &drops)).is_ok());
+        assert!(q.push(PanicOnClone(true, &drops)).is_ok());
+        assert_eq!(drops.get(), 1);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| q.clone()));
+        assert!(result.is_err());
+        // The first two elements were cloned, and their clones are dropped exactly once
+        assert_eq!(drops.get(), 3);
+        drop(q);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn clone_wrapped() {
+        let mut q1 = Queue::<String, 3>::new();
+        assert_eq!(q1.push("a".to_string()), Ok(()));
+        assert_eq!(q1.push("b".to_string()), Ok(()));
+        assert_eq!(q1.pop(), Some("a".to_string()));
+        assert_eq!(q1.push("c".to_string()), Ok(()));
+        assert_eq!(q1.push("d".to_string()), Ok(()));
+
+        let mut q2 = q1.clone();
+        for q in [&mut q1, &mut q2] {
+            assert_eq!(q.pop(), Some("b".to_string()));
+            assert_eq!(q.pop(), Some("c".to_string()));
+            assert_eq!(q.pop(), Some("d".to_string()));
+            assert_eq!(q.pop(), None);
+        }
+    }
+
+    #[test]
+    fn debug() {
+        let mut q = Queue::<i32, 3>::new();
+        assert_eq!(format!("{:?}", q), "[]");
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.push(4), Ok(()));
+        assert_eq!(format!("{:?}", q), "[2, 3, 4]");
+    }
+
+    #[test]
+    fn drain_filter() {
+        let drops = Cell::new(0);
+        let mut q = Queue::<(i32, DropCounter), 5>::new();
+        for v in 0..3 {
+            assert!(q.push((v, DropCounter(&drops))).is_ok());
+        }
+        for v in 3..8 {
+            q.push_overwrite((v, DropCounter(&drops)));
+        }
+        assert_eq!(drops.get(), 3);
+
+        q.drain_filter(|(v, _)| v % 2 == 0);
+        assert_eq!(drops.get(), 5);
+        assert_eq!(q.size(), 3);
+        assert_eq!(q.push((8, DropCounter(&drops))).is_ok(), true);
+        for v in [3, 5, 7, 8] {
+            assert_eq!(q.pop().map(|(v, _)| v), Some(v));
+        }
+        assert_eq!(q.pop().is_none(), true);
+
+        let mut q = Queue::<i32, 3>::try_from_iter([1, 2, 3]).unwrap();
+        q.drain_filter(|_| false);
+        assert_eq!(q.is_full(), true);
+        q.drain_filter(|_| true);
+        assert_eq!(q.is_empty(), true);
+        assert_eq!(q.push(4), Ok(()));
+        assert_eq!(q.pop(), Some(4));
+    }
+
+    #[test]
+    fn as_contiguous_slice() {
+        let mut q = Queue::<i32, 4>::new();
+        assert_eq!(q.as_contiguous_slice(), Some(&[][..]));
+
+        assert_eq!(q.push_slice(&[1, 2, 3, 4]), Ok(4));
+        assert_eq!(q.as_contiguous_slice(), Some(&[1, 2, 3, 4][..]));
+
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.pop(), Some(2));
+        assert_eq!(q.as_contiguous_slice(), Some(&[3, 4][..]));
+        assert_eq!(q.push(5), Ok(()));
+        assert_eq!(q.as_contiguous_slice(), None);
+
+        q.make_contiguous();
+        assert_eq!(q.as_contiguous_slice(), Some(&[3, 4, 5][..]));
+    }
+
+    #[test]
+    fn as_slices() {
+        let mut q = Queue::<i32, 4>::new();
+        assert_eq!(q.as_slices(), (&[][..], &[][..]));
+
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.as_slices(), (&[1, 2, 3][..], &[][..]));
+
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.pop(), Some(2));
+        assert_eq!(q.push(4), Ok(()));
+        assert_eq!(q.as_slices(), (&[3, 4][..], &[][..]));
+        assert_eq!(q.push(5), Ok(()));
+        assert_eq!(q.push(6), Ok(()));
+        assert_eq!(q.as_slices(), (&[3, 4][..], &[5, 6][..]));
+
+        let (front, back) = q.as_slices();
+        let expected = [front, back].concat();
+        let mut popped = vec![];
+        while let Some(v) = q.pop() {
+            popped.push(v);
+        }
+        assert_eq!(expected, popped);
+    }
+
+    #[test]
+    fn iter() {
+        let mut q = Queue::<i32, 3>::new();
+        assert_eq!(q.iter().len(), 0);
+        assert_eq!(q.iter().next(), None);
+
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.iter().len(), 2);
+        assert!(q.iter().copied().eq([1, 2]));
+
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.push(4), Ok(()));
+        let items = q.iter().copied().collect::<Vec<_>>();
+        assert_eq!(items, [2, 3, 4]);
+        let mut popped = vec![];
+        while let Some(v) = q.pop() {
+            popped.push(v);
+        }
+        assert_eq!(items, popped);
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut q = Queue::<i32, 3>::new();
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.push(4), Ok(()));
+        let iter = q.into_iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.collect::<Vec<_>>(), [2, 3, 4]);
+
+        let drops = Cell::new(0);
+        let mut q = Queue::<DropCounter, 3>::new();
+        for _ in 0..3 {
+            assert!(q.push(DropCounter(&drops)).is_ok());
+        }
+        let mut iter = q.into_iter();
+        drop(iter.next());
+        assert_eq!(drops.get(), 1);
+        drop(iter);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn clear() {
+        let drops = Cell::new(0);
+        let mut q = Queue::<DropCounter, 3>::new();
+        q.clear();
+        assert_eq!(drops.get(), 0);
+
+        for _ in 0..3 {
+            assert!(q.push(DropCounter(&drops)).is_ok());
+        }
+        drop(q.pop());
+        assert!(q.push(DropCounter(&drops)).is_ok());
+        assert_eq!(drops.get(), 1);
+        q.clear();
+        assert_eq!(drops.get(), 4);
+        assert_eq!(q.size(), 0);
+        assert_eq!(q.is_empty(), true);
+
+        for _ in 0..2 {
+            assert!(q.push(DropCounter(&drops)).is_ok());
+        }
+        drop(q);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn eq() {
+        let mut q1 = Queue::<i32, 3>::new();
+        let mut q2 = Queue::<i32, 3>::new();
+        assert_eq!(q1, q2);
+
+        assert_eq!(q1.push(1), Ok(()));
+        assert_eq!(q1.push(2), Ok(()));
+        assert_eq!(q1.push(3), Ok(()));
+        assert_eq!(q2.push(0), Ok(()));
+        assert_eq!(q2.push(1), Ok(()));
+        assert_eq!(q2.pop(), Some(0));
+        assert_eq!(q2.push(2), Ok(()));
+        assert_ne!(q1, q2);
+        assert_eq!(q2.push(3), Ok(()));
+        assert_eq!(q1, q2);
+        assert_ne!(q1.r, q2.r);
+
+        assert_eq!(q1.pop(), Some(1));
+        assert_eq!(q1.push(4), Ok(()));
+        assert_ne!(q1, q2);
+    }
+
+    #[test]
+    fn from_iter() {
+        let q = Queue::<i32, 3>::try_from_iter([1, 2, 3]).unwrap();
+        assert!(q.iter().copied().eq([1, 2, 3]));
+        let q = Queue::<i32, 3>::try_from_iter([1]).unwrap();
+        assert!(q.iter().copied().eq([1]));
+        assert_eq!(
+            Queue::<i32, 3>::try_from_iter([1, 2, 3, 4]),
+            Err(Error::Full)
+        );
+
+        let q = Queue::<i32, 3>::from_iter_overwriting([1, 2]);
+        assert!(q.iter().copied().eq([1, 2]));
+        let q = Queue::<i32, 3>::from_iter_overwriting(1..=5);
+        assert!(q.iter().copied().eq([3, 4, 5]));
+
+        let drops = Cell::new(0);
+        let res = Queue::<DropCounter, 3>::try_from_iter((0..4).map(|_| DropCounter(&drops)));
+        assert!(res.is_err());
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn peek_mut_and_back() {
+        let mut q = Queue::<i32, 3>::new();
+        assert_eq!(q.peek_mut(), None);
+        assert_eq!(q.peek_back(), None);
+
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.peek_back(), Some(&1));
+        *q.peek_mut().unwrap() = 10;
+        assert_eq!(q.peek(), Some(&10));
+        assert_eq!(q.peek_back(), Some(&10));
+
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.pop(), Some(10));
+        assert_eq!(q.push(4), Ok(()));
+        assert_eq!(q.peek_back(), Some(&4));
+        *q.peek_mut().unwrap() = 20;
+        assert_eq!(q.pop(), Some(20));
+        assert_eq!(q.peek_back(), Some(&4));
+    }
+
+    #[test]
+    fn get_and_contains() {
+        let mut q = Queue::<i32, 3>::new();
+        assert_eq!(q.get(0), None);
+        assert_eq!(q.contains(&1), false);
+
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.push(4), Ok(()));
+        assert_eq!(q.get(0), Some(&2));
+        assert_eq!(q.get(1), Some(&3));
+        assert_eq!(q.get(2), Some(&4));
+        assert_eq!(q.get(3), None);
+        assert_eq!(q.contains(&1), false);
+        assert_eq!(q.contains(&4), true);
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn high_water_mark() {
+        let mut q = Queue::<i32, 4>::new();
+        assert_eq!(q.high_water_mark(), 0);
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.push(4), Ok(()));
+        assert_eq!(q.high_water_mark(), 3);
+        assert_eq!(q.pop(), Some(2));
+        assert_eq!(q.pop(), Some(3));
+        assert_eq!(q.high_water_mark(), 3);
+
+        q.reset_high_water_mark();
+        assert_eq!(q.high_water_mark(), 1);
+        assert_eq!(q.push_slice(&[5, 6, 7]), Ok(3));
+        assert_eq!(q.push_overwrite(8), Some(4));
+        assert_eq!(q.high_water_mark(), 4);
+    }
+
+    #[test]
+    fn small_queue() {
+        let q = SmallQueue::<u8>::default();
+        assert_eq!(q.capacity(), 16);
+        assert_eq!(SmallQueue::<u8>::CAPACITY, 16);
+        assert_eq!(q.is_empty(), true);
+    }
+
+    #[test]
+    fn try_pop() {
+        let mut q = Queue::<i32, 3>::new();
+        assert_eq!(q.try_pop(), Err(Error::Empty));
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.try_pop(), Ok(1));
+        assert_eq!(q.try_pop(), Err(Error::Empty));
+    }
+
+    #[test]
+    fn deque() {
+        let mut q = Queue::<i32, 3>::new();
+        assert_eq!(q.pop_back(), None);
+
+        assert_eq!(q.push_front(1), Ok(()));
+        assert_eq!(q.peek(), Some(&1));
+        assert_eq!(q.peek_back(), Some(&1));
+        assert_eq!(q.push_front(0), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.push_front(-1), Err(Error::Full));
+        assert_eq!(q.push(3), Err(Error::Full));
+        assert!(q.iter().copied().eq([0, 1, 2]));
+
+        assert_eq!(q.pop_back(), Some(2));
+        assert_eq!(q.pop_back(), Some(1));
+        assert_eq!(q.pop_back(), Some(0));
+        assert_eq!(q.pop_back(), None);
+        assert_eq!(q.is_empty(), true);
+
+        // Wrap around in both directions
+        for i in 0..10 {
+            assert_eq!(q.push(i), Ok(()));
+            assert_eq!(q.push_front(-i), Ok(()));
+            assert_eq!(q.pop_back(), Some(i));
+            assert_eq!(q.pop(), Some(-i));
+            assert_eq!(q.is_empty(), true);
+        }
+
+        assert_eq!(q.push_front(2), Ok(()));
+        assert_eq!(q.push_front(1), Ok(()));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.is_full(), true);
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.pop_back(), Some(3));
+        assert_eq!(q.pop(), Some(2));
+        assert_eq!(q.size(), 0);
+    }
+
+    #[test]
+    fn push_slice() {
+        let mut q = Queue::<u8, 4>::new();
+        assert_eq!(q.push_slice(&[]), Ok(0));
+        assert_eq!(q.push_slice(&[1, 2]), Ok(2));
+        assert!(q.iter().copied().eq([1, 2]));
+
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.pop(), Some(2));
+        assert_eq!(q.push_slice(&[3, 4, 5]), Ok(3));
+        assert_eq!(q.as_slices(), (&[3, 4][..], &[5][..]));
+
+        assert_eq!(q.push_slice(&[6, 7]), Ok(1));
+        assert_eq!(q.push_slice(&[7]), Err(Error::Full));
+        assert_eq!(q.is_full(), true);
+        assert_eq!(q.peek_back(), Some(&6));
+        assert!(q.iter().copied().eq([3, 4, 5, 6]));
+        assert_eq!(q.push(7), Err(Error::Full));
+        assert_eq!(q.pop(), Some(3));
+        assert_eq!(q.push(7), Ok(()));
+        assert!(q.iter().copied().eq([4, 5, 6, 7]));
+    }
+
+    #[test]
+    fn make_contiguous() {
+        let mut q = Queue::<i32, 4>::new();
+        assert_eq!(q.make_contiguous(), &[] as &[i32]);
+
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.push(3), Ok(()));
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.pop(), Some(2));
+        assert_eq!(q.push(4), Ok(()));
+        assert_eq!(q.push(5), Ok(()));
+        assert_eq!(q.as_slices(), (&[3, 4][..], &[5][..]));
+        assert_eq!(q.make_contiguous(), &[3, 4, 5]);
+        assert_eq!(q.as_slices(), (&[3, 4, 5][..], &[][..]));
+
+        assert_eq!(q.push(6), Ok(()));
+        assert_eq!(q.make_contiguous(), &[3, 4, 5, 6]);
+        assert_eq!(q.pop(), Some(3));
+        assert_eq!(q.push(7), Ok(()));
+        assert_eq!(q.pop(), Some(4));
+        assert_eq!(q.pop(), Some(5));
+        assert_eq!(q.pop(), Some(6));
+        assert_eq!(q.pop(), Some(7));
+        assert_eq!(q.pop(), None);
+    }
+
+    #[test]
+    fn split() {
+        let mut q = Queue::<i32, 3>::new();
+        {
+            let (mut p, mut c) = q.split();
+            assert_eq!(c.peek(), None);
+            assert_eq!(c.pop(), None);
+
+            assert_eq!(p.push(1), Ok(()));
+            assert_eq!(p.push(2), Ok(()));
+            assert_eq!(c.peek(), Some(&1));
+            assert_eq!(c.pop(), Some(1));
+            assert_eq!(p.push(3), Ok(()));
+            assert_eq!(p.push(4), Ok(()));
+            assert_eq!(p.push(5), Err(Error::Full));
+            assert_eq!(c.pop(), Some(2));
+            assert_eq!(c.pop(), Some(3));
+            assert_eq!(p.push(5), Ok(()));
+            assert_eq!(c.peek(), Some(&4));
+        }
+        assert!(q.iter().copied().eq([4, 5]));
+    }
+
+    #[test]
+    fn split_peek() {
+        #[derive(Debug, PartialEq)]
+        struct NoClone(std::string::String);
+
+        let mut q = Queue::<NoClone, 2>::new();
+        let (mut p, mut c) = q.split();
+        assert!(p.push(NoClone("a".into())).is_ok());
+        let front = c.peek().unwrap();
+        assert!(p.push(NoClone("b".into())).is_ok());
+        assert!(p.push(NoClone("c".into())).is_err());
+        assert_eq!(front.0, "a");
+        assert_eq!(c.pop(), Some(NoClone("a".into())));
+        assert_eq!(c.peek(), Some(&NoClone("b".into())));
+    }
+
     #[test]
     fn queue() {
         let mut q = Queue::<i32, 3>::new();
@@ -2,26 +2,67 @@ use {
     crate::Error,
     core::{
         fmt,
+        hash::{Hash, Hasher},
         mem::{self, MaybeUninit},
+        ops::{Index, IndexMut},
+        ptr, slice,
     },
 };
 
+/// A `Stack` with room for 16 elements
+pub type SmallStack<T> = Stack<T, 16>;
+
 pub struct Stack<T, const N: usize> {
     buf: [MaybeUninit<T>; N],
     size: usize,
 }
 
 impl<T, const N: usize> Stack<T, N> {
+    pub const CAPACITY: usize = N;
+
     pub const fn new() -> Self {
         Self {
-            buf: MaybeUninit::uninit_array::<N>(),
+            buf: [const { MaybeUninit::uninit() }; N],
             size: 0,
         }
     }
 
+    /// Build a stack from a buffer and the number of elements in it.
+    ///
+    /// # Safety
+    /// `buf[0..size]` must be initialized and `size` must be at most `N`.
+    pub const unsafe fn from_raw_parts(buf: [MaybeUninit<T>; N], size: usize) -> Self {
+        Self { buf, size }
+    }
+
+    /// Build a stack from an iterator. Returns `Err(Error::Full)` if the iterator
+    /// yields more than `N` items, dropping the items consumed so far.
+    pub fn try_from_iter<I: IntoIterator<Item = T>>(iter: I) -> Result<Self, Error> {
+        let mut stack = Self::new();
+        for item in iter {
+            stack.push(item)?;
+        }
+        Ok(stack)
+    }
+
+    /// Build a stack from the first `N` items of an iterator, ignoring the rest.
+    pub fn from_iter_truncating<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stack = Self::new();
+        for item in iter.into_iter().take(N) {
+            // Can't fail, at most N items are taken
+            let _ = stack.push(item);
+        }
+        stack
+    }
+
     pub fn push(&mut self, item: T) -> Result<(), Error> {
+        self.try_push(item).map_err(|_| Error::Full)
+    }
+
+    /// Like `push`, but hands the item back if the stack is full.
+    pub fn try_push(&mut self, item: T) -> Result<(), T> {
         match self.is_full() {
-            true => Err(Error::Full),
+            true => Err(item),
             false => {
                 self.buf[self.size].write(item);
                 self.size += 1;
@@ -30,6 +71,31 @@ impl<T, const N: usize> Stack<T, N> {
         }
     }
 
+    /// Like `push`, but drops the item if the stack is full. Returns whether it was pushed.
+    pub fn push_saturating(&mut self, item: T) -> bool {
+        self.try_push(item).is_ok()
+    }
+
+    /// Push clones of `value` until the stack is full.
+    pub fn fill(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        while self.size < N {
+            self.buf[self.size].write(value.clone());
+            self.size += 1;
+        }
+    }
+
+    /// Push all items from an iterator. Returns `Err(Error::Full)` if the stack
+    /// fills up before the iterator is exhausted. Items pushed before that are kept.
+    pub fn extend_checked<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Result<(), Error> {
+        for item in iter {
+            self.push(item)?;
+        }
+        Ok(())
+    }
+
     pub fn pop(&mut self) -> Option<T> {
         match self.is_empty() {
             true => None,
@@ -42,6 +108,69 @@ impl<T, const N: usize> Stack<T, N> {
         }
     }
 
+    /// Pop the top element only if `pred` returns `true` for it.
+    pub fn pop_if<F: FnOnce(&T) -> bool>(&mut self, pred: F) -> Option<T> {
+        match pred(self.peek()?) {
+            true => self.pop(),
+            false => None,
+        }
+    }
+
+    /// Remove the element at `index` by swapping it with the top element and popping.
+    /// Doesn't preserve order, but is O(1).
+    pub fn swap_remove(&mut self, index: usize) -> Option<T> {
+        match index < self.size {
+            true => {
+                self.buf.swap(index, self.size - 1);
+                self.pop()
+            }
+            false => None,
+        }
+    }
+
+    /// Insert an item at `index`, moving all elements above it up by one.
+    ///
+    /// Panics if `index > size()`.
+    pub fn insert(&mut self, index: usize, item: T) -> Result<(), Error> {
+        assert!(index <= self.size, "index out of range");
+        if self.is_full() {
+            return Err(Error::Full);
+        }
+        // SAFETY: buf[index..size] is initialized and there's room for one more element,
+        // so it can be moved up by one
+        unsafe {
+            let p = self.buf.as_mut_ptr().add(index);
+            ptr::copy(p, p.add(1), self.size - index);
+        }
+        self.buf[index].write(item);
+        self.size += 1;
+        Ok(())
+    }
+
+    /// Remove the element at `index`, moving all elements above it down by one.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        match index < self.size {
+            true => {
+                // SAFETY: buf[index] is initialized and ownership is moved out before
+                // buf[index + 1..size] is moved down over it
+                let item = unsafe {
+                    let p = self.buf.as_mut_ptr().add(index);
+                    let item = p.read().assume_init();
+                    ptr::copy(p.add(1), p, self.size - index - 1);
+                    item
+                };
+                self.size -= 1;
+                Some(item)
+            }
+            false => None,
+        }
+    }
+
+    /// Like `pop`, but returns `Err(Error::Empty)` instead of `None`.
+    pub fn try_pop(&mut self) -> Result<T, Error> {
+        self.pop().ok_or(Error::Empty)
+    }
+
     pub const fn peek(&self) -> Option<&T> {
         match self.is_empty() {
             true => None,
@@ -49,6 +178,51 @@ impl<T, const N: usize> Stack<T, N> {
         }
     }
 
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        match self.is_empty() {
+            true => None,
+            false => Some(unsafe { self.buf[self.size - 1].assume_init_mut() }),
+        }
+    }
+
+    /// Get the element at `index`, where index 0 is the bottom of the stack.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+
+    /// Get the element at `index` mutably, where index 0 is the bottom of the stack.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.as_mut_slice().get_mut(index)
+    }
+
+    /// Binary search a sorted stack, see `slice::binary_search`.
+    pub fn binary_search(&self, x: &T) -> Result<usize, usize>
+    where
+        T: Ord,
+    {
+        self.as_slice().binary_search(x)
+    }
+
+    /// Insert an item into a sorted stack, keeping it sorted. Returns the index of the item.
+    pub fn insert_sorted(&mut self, x: T) -> Result<usize, Error>
+    where
+        T: Ord,
+    {
+        if self.is_full() {
+            return Err(Error::Full);
+        }
+        let index = self.binary_search(&x).unwrap_or_else(|i| i);
+        self.insert(index, x)?;
+        Ok(index)
+    }
+
+    pub fn contains(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.as_slice().contains(value)
+    }
+
     pub fn as_slice(&self) -> &[T] {
         // SAFETY: buf[0..size] is initialized memory
         unsafe { mem::transmute(&self.buf[0..self.size]) }
@@ -59,6 +233,136 @@ impl<T, const N: usize> Stack<T, N> {
         unsafe { mem::transmute(&mut self.buf[0..self.size]) }
     }
 
+    /// Pointer to the bottom of the stack, valid for reads of `size()` elements.
+    pub const fn as_ptr(&self) -> *const T {
+        self.buf.as_ptr() as *const T
+    }
+
+    /// Pointer to the bottom of the stack, valid for reads and writes of `size()` elements.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.buf.as_mut_ptr() as *mut T
+    }
+
+    /// Iterate from the bottom to the top of the stack, i.e. in insertion order.
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    /// Iterate mutably from the bottom to the top of the stack, i.e. in insertion order.
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    /// Convert a full stack into an array. A stack that isn't full is handed back.
+    pub fn into_array(self) -> Result<[T; N], Self> {
+        match self.is_full() {
+            true => {
+                // SAFETY: The stack is full so all of buf is initialized, and MaybeUninit<T>
+                // has the same layout as T. self is forgotten so nothing is dropped twice.
+                let array = unsafe { ptr::read(&self.buf as *const _ as *const [T; N]) };
+                mem::forget(self);
+                Ok(array)
+            }
+            false => Err(self),
+        }
+    }
+
+    /// Keep only the elements for which `f` returns `true`, preserving their order.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let len = self.size;
+        // Elements not yet visited are leaked rather than double dropped if `f` panics
+        self.size = 0;
+        for i in 0..len {
+            // SAFETY: buf[i] is initialized and hasn't been moved or dropped yet
+            if f(unsafe { self.buf[i].assume_init_ref() }) {
+                self.buf.swap(self.size, i);
+                self.size += 1;
+            } else {
+                // SAFETY: See above
+                unsafe { self.buf[i].assume_init_drop() };
+            }
+        }
+    }
+
+    /// Remove consecutive duplicate elements, keeping the first of each run.
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        let len = self.size;
+        if len == 0 {
+            return;
+        }
+        // Elements not yet visited are leaked rather than double dropped if `eq` panics
+        self.size = 1;
+        for i in 1..len {
+            // SAFETY: buf[0..size] and buf[i] are initialized, and buf[i] hasn't been moved
+            // or dropped yet
+            let duplicate = unsafe {
+                self.buf[i].assume_init_ref() == self.buf[self.size - 1].assume_init_ref()
+            };
+            match duplicate {
+                // SAFETY: See above
+                true => unsafe { self.buf[i].assume_init_drop() },
+                false => {
+                    self.buf.swap(self.size, i);
+                    self.size += 1;
+                }
+            }
+        }
+    }
+
+    /// Move the elements at `at..size` into a new stack, leaving `0..at` in `self`.
+    ///
+    /// Panics if `at > size`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.size, "split_off index out of bounds");
+        let mut other = Self::new();
+        let count = self.size - at;
+        // SAFETY: buf[at..size] is initialized, and ownership is moved to other by
+        // shrinking self.size
+        unsafe {
+            ptr::copy_nonoverlapping(self.buf.as_ptr().add(at), other.buf.as_mut_ptr(), count)
+        };
+        self.size = at;
+        other.size = count;
+        other
+    }
+
+    /// Move all elements of `other` onto the top of `self`, preserving their order.
+    /// Returns `Err(Error::Full)` and leaves both stacks untouched if they don't fit.
+    pub fn append<const M: usize>(&mut self, other: &mut Stack<T, M>) -> Result<(), Error> {
+        if other.size > N - self.size {
+            return Err(Error::Full);
+        }
+        // SAFETY: other.buf[0..other.size] is initialized and fits in self.buf[self.size..N].
+        // Ownership is moved to self by setting other.size to 0
+        unsafe {
+            ptr::copy_nonoverlapping(
+                other.buf.as_ptr(),
+                self.buf.as_mut_ptr().add(self.size),
+                other.size,
+            )
+        };
+        self.size += other.size;
+        other.size = 0;
+        Ok(())
+    }
+
+    /// Reverse the order of the elements in place.
+    pub fn reverse(&mut self) {
+        self.as_mut_slice().reverse();
+    }
+
+    /// Drop all elements, leaving the stack empty.
+    pub fn clear(&mut self) {
+        let initialized: *mut [T] = self.as_mut_slice();
+        self.size = 0;
+        // SAFETY: initialized points to buf[0..size], and size is reset before dropping
+        // so a panicking destructor can't cause a double drop.
+        unsafe { ptr::drop_in_place(initialized) }
+    }
+
     pub const fn capacity(&self) -> usize {
         N
     }
@@ -71,41 +375,195 @@ impl<T, const N: usize> Stack<T, N> {
         self.size == N
     }
 
+    /// Whether `n` more elements can be pushed without the stack filling up.
+    pub const fn has_room_for(&self, n: usize) -> bool {
+        self.size.saturating_add(n) <= N
+    }
+
     pub const fn is_empty(&self) -> bool {
         self.size == 0
     }
 }
 
+impl<T, const N: usize> Default for Stack<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for Stack<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
 impl<T: fmt::Debug, const N: usize> fmt::Debug for Stack<T, N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Stack")
             .field("buf", &unsafe {
-                mem::transmute::<_, &[T]>(&self.buf[0..self.size])
+                mem::transmute::<&[MaybeUninit<T>], &[T]>(&self.buf[0..self.size])
             })
             .field("size", &self.size)
             .finish()
     }
 }
 
+#[cfg(feature = "defmt")]
+impl<T: defmt::Format, const N: usize> defmt::Format for Stack<T, N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=[?]}", self.as_slice())
+    }
+}
+
 impl<T: Clone, const N: usize> Clone for Stack<T, N> {
     fn clone(&self) -> Self {
-        let mut new = Self {
-            buf: MaybeUninit::uninit_array::<N>(),
-            size: self.size,
-        };
+        let mut new = Self::new();
 
-        for i in 0..self.size {
-            // SAFETY: We know that 0..self.size is initialized memory
-            new.buf[i].write(unsafe { self.buf[i].assume_init_ref() }.clone());
+        // size is only raised after each element is written, so if a clone panics
+        // only the elements cloned so far are dropped
+        for item in self.as_slice() {
+            new.buf[new.size].write(item.clone());
+            new.size += 1;
         }
 
         new
     }
+
+    /// Reuses the elements in `self` with `T::clone_from`, only cloning or dropping the
+    /// elements past the smaller of the two sizes.
+    fn clone_from(&mut self, source: &Self) {
+        let common = self.size.min(source.size);
+        for (dst, src) in self.as_mut_slice()[..common]
+            .iter_mut()
+            .zip(source.as_slice())
+        {
+            dst.clone_from(src);
+        }
+
+        let tail: *mut [T] = &mut self.as_mut_slice()[common..];
+        self.size = common;
+        // SAFETY: tail points to buf[common..size], and size is reduced before dropping
+        // so a panicking destructor can't cause a double drop.
+        unsafe { ptr::drop_in_place(tail) }
+
+        for item in &source.as_slice()[common..] {
+            self.buf[self.size].write(item.clone());
+            self.size += 1;
+        }
+    }
+}
+
+impl<T, const N: usize> Index<usize> for Stack<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for Stack<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
+impl<T: Clone, const N: usize> TryFrom<&[T]> for Stack<T, N> {
+    type Error = Error;
+
+    fn try_from(slice: &[T]) -> Result<Self, Self::Error> {
+        match slice.len() > N {
+            true => Err(Error::Full),
+            false => Self::try_from_iter(slice.iter().cloned()),
+        }
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for Stack<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for Stack<T, N> {}
+
+impl<T: Hash, const N: usize> Hash for Stack<T, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.size.hash(state);
+        for item in self.iter() {
+            item.hash(state);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, const N: usize> serde::Serialize for Stack<T, N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>, const N: usize> serde::Deserialize<'de> for Stack<T, N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StackVisitor<T, const N: usize>(core::marker::PhantomData<T>);
+
+        impl<'de, T: serde::Deserialize<'de>, const N: usize> serde::de::Visitor<'de>
+            for StackVisitor<T, N>
+        {
+            type Value = Stack<T, N>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a sequence of at most {} elements", N)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut len = 0;
+                let mut error = None;
+                let items = core::iter::from_fn(|| match seq.next_element() {
+                    Ok(item) => {
+                        len += item.is_some() as usize;
+                        item
+                    }
+                    Err(e) => {
+                        error = Some(e);
+                        None
+                    }
+                });
+                let stack = Stack::try_from_iter(items);
+                if let Some(e) = error {
+                    return Err(e);
+                }
+                match stack {
+                    Ok(stack) => Ok(stack),
+                    Err(_) => {
+                        // Count the rest of the sequence to report its real length
+                        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+                            len += 1;
+                        }
+                        Err(serde::de::Error::invalid_length(len, &self))
+                    }
+                }
+            }
+        }
+
+        deserializer.deserialize_seq(StackVisitor(core::marker::PhantomData))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, core::cell::Cell};
+
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
 
     #[test]
     fn clone() {
@@ -130,6 +588,609 @@ mod tests {
         assert_eq!(s1.is_empty(), false);
     }
 
+    #[test]
+    fn clone_panic() {
+        struct PanicOnClone<'a>(bool, &'a Cell<usize>);
+
+        impl Clone for PanicOnClone<'_> {
+            fn clone(&self) -> Self {
+                assert!(!self.0, "clone failed");
+                Self(self.0, self.1)
+            }
+        }
+
+        impl Drop for PanicOnClone<'_> {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let s = Stack::<_, 3>::try_from_iter([
+            PanicOnClone(false, &drops),
+            PanicOnClone(true, &drops),
+            PanicOnClone(false, &drops),
+        ])
+        .unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| s.clone()));
+        assert!(result.is_err());
+        // Only the first element was cloned, and its clone is dropped exactly once
+        assert_eq!(drops.get(), 1);
+        drop(s);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn clone_from() {
+        struct Tracked<'a> {
+            v: i32,
+            clones: &'a Cell<usize>,
+            drops: &'a Cell<usize>,
+        }
+
+        impl Clone for Tracked<'_> {
+            fn clone(&self) -> Self {
+                self.clones.set(self.clones.get() + 1);
+                Self { ..*self }
+            }
+
+            fn clone_from(&mut self, source: &Self) {
+                self.v = source.v;
+            }
+        }
+
+        impl Drop for Tracked<'_> {
+            fn drop(&mut self) {
+                self.drops.set(self.drops.get() + 1);
+            }
+        }
+
+        let (clones, drops) = (Cell::new(0), Cell::new(0));
+        let tracked = |v| Tracked {
+            v,
+            clones: &clones,
+            drops: &drops,
+        };
+        let values = |s: &Stack<Tracked, 3>| s.iter().map(|t| t.v).collect::<Vec<_>>();
+
+        let long = Stack::<_, 3>::try_from_iter([tracked(1), tracked(2), tracked(3)]).unwrap();
+        let short = Stack::<_, 3>::try_from_iter([tracked(4)]).unwrap();
+
+        let mut s = Stack::<_, 3>::try_from_iter([tracked(5), tracked(6)]).unwrap();
+        s.clone_from(&long);
+        assert_eq!(values(&s), [1, 2, 3]);
+        assert_eq!((clones.get(), drops.get()), (1, 0));
+
+        s.clone_from(&short);
+        assert_eq!(values(&s), [4]);
+        assert_eq!((clones.get(), drops.get()), (1, 2));
+
+        drop(s);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn iter() {
+        let mut s = Stack::<i32, 3>::new();
+        assert_eq!(s.iter().next(), None);
+        assert_eq!(s.iter_mut().next(), None);
+
+        assert_eq!(s.push(1), Ok(()));
+        assert_eq!(s.push(2), Ok(()));
+        assert_eq!(s.push(3), Ok(()));
+        assert!(s.iter().eq(s.as_slice().iter()));
+        assert!(s.iter().copied().eq([1, 2, 3]));
+
+        for v in s.iter_mut() {
+            *v *= 10;
+        }
+        assert_eq!(s.peek(), Some(&30));
+        assert_eq!(s.as_slice(), &[10, 20, 30]);
+    }
+
+    #[test]
+    fn clear() {
+        let drops = Cell::new(0);
+        let mut s = Stack::<DropCounter, 3>::new();
+
+        s.clear();
+        assert_eq!(drops.get(), 0);
+
+        assert!(s.push(DropCounter(&drops)).is_ok());
+        assert!(s.push(DropCounter(&drops)).is_ok());
+        s.clear();
+        assert_eq!(drops.get(), 2);
+        assert_eq!(s.size(), 0);
+        assert_eq!(s.is_empty(), true);
+        assert_eq!(s.capacity(), 3);
+
+        assert!(s.push(DropCounter(&drops)).is_ok());
+        drop(s);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn eq() {
+        let mut s1 = Stack::<i32, 3>::new();
+        let mut s2 = Stack::<i32, 3>::new();
+        assert_eq!(s1, s2);
+
+        assert_eq!(s1.push(1), Ok(()));
+        assert_ne!(s1, s2);
+        assert_eq!(s2.push(1), Ok(()));
+        assert_eq!(s1, s2);
+
+        assert_eq!(s1.push(2), Ok(()));
+        assert_eq!(s2.push(3), Ok(()));
+        assert_ne!(s1, s2);
+
+        assert_eq!(s2.pop(), Some(3));
+        assert_eq!(s2.push(2), Ok(()));
+        assert_eq!(s1, s2);
+    }
+
+    #[test]
+    fn from_iter() {
+        let s = Stack::<i32, 3>::try_from_iter([1, 2, 3]).unwrap();
+        assert_eq!(s.as_slice(), &[1, 2, 3]);
+        let s = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        assert_eq!(s.as_slice(), &[1, 2]);
+        assert_eq!(
+            Stack::<i32, 3>::try_from_iter([1, 2, 3, 4]),
+            Err(Error::Full)
+        );
+
+        let s = Stack::<i32, 3>::from_iter_truncating([1, 2, 3]);
+        assert_eq!(s.as_slice(), &[1, 2, 3]);
+        let s = Stack::<i32, 3>::from_iter_truncating([1, 2]);
+        assert_eq!(s.as_slice(), &[1, 2]);
+        let s = Stack::<i32, 3>::from_iter_truncating(1..);
+        assert_eq!(s.as_slice(), &[1, 2, 3]);
+
+        let drops = Cell::new(0);
+        let res = Stack::<DropCounter, 3>::try_from_iter((0..4).map(|_| DropCounter(&drops)));
+        assert!(res.is_err());
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn try_from_slice() {
+        let s = Stack::<i32, 3>::try_from(&[1, 2, 3][..]).unwrap();
+        assert_eq!(s.as_slice(), &[1, 2, 3]);
+        assert_eq!(s.is_full(), true);
+        let s = Stack::<i32, 3>::try_from(&[1][..]).unwrap();
+        assert_eq!(s.as_slice(), &[1]);
+        assert_eq!(
+            Stack::<i32, 3>::try_from(&[1, 2, 3, 4][..]),
+            Err(Error::Full)
+        );
+    }
+
+    #[test]
+    fn extend_checked() {
+        let mut s = Stack::<i32, 5>::new();
+        assert_eq!(s.extend_checked([1, 2]), Ok(()));
+        assert_eq!(s.as_slice(), &[1, 2]);
+        assert_eq!(s.extend_checked([]), Ok(()));
+        assert_eq!(s.extend_checked([3, 4, 5, 6, 7]), Err(Error::Full));
+        assert_eq!(s.size(), 5);
+        assert_eq!(s.as_slice(), &[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn retain() {
+        let mut s = Stack::<i32, 5>::try_from_iter([1, 2, 3, 4, 5]).unwrap();
+        s.retain(|_| true);
+        assert_eq!(s.as_slice(), &[1, 2, 3, 4, 5]);
+        s.retain(|v| v % 2 == 1);
+        assert_eq!(s.as_slice(), &[1, 3, 5]);
+        s.retain(|_| false);
+        assert_eq!(s.as_slice(), &[] as &[i32]);
+        assert_eq!(s.is_empty(), true);
+
+        let drops = Cell::new(0);
+        let mut s = Stack::<DropCounter, 4>::new();
+        for _ in 0..4 {
+            assert!(s.push(DropCounter(&drops)).is_ok());
+        }
+        let mut idx = 0;
+        s.retain(|_| {
+            idx += 1;
+            idx % 2 == 0
+        });
+        assert_eq!(drops.get(), 2);
+        assert_eq!(s.size(), 2);
+        drop(s);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn peek_mut() {
+        let mut s = Stack::<i32, 3>::new();
+        assert_eq!(s.peek_mut(), None);
+        assert_eq!(s.push(1), Ok(()));
+        assert_eq!(s.push(2), Ok(()));
+        *s.peek_mut().unwrap() = 20;
+        assert_eq!(s.pop(), Some(20));
+        assert_eq!(s.pop(), Some(1));
+    }
+
+    #[test]
+    fn get() {
+        let mut s = Stack::<i32, 4>::try_from_iter([1, 2, 3]).unwrap();
+        assert_eq!(s.get(0), Some(&1));
+        assert_eq!(s.get(1), Some(&2));
+        assert_eq!(s.get(2), Some(&3));
+        assert_eq!(s.get(3), None);
+        assert_eq!(s.get(usize::MAX), None);
+
+        *s.get_mut(0).unwrap() = 10;
+        *s.get_mut(2).unwrap() = 30;
+        assert_eq!(s.get_mut(3), None);
+        assert_eq!(s.as_slice(), &[10, 2, 30]);
+        assert_eq!(s.peek(), Some(&30));
+    }
+
+    #[test]
+    fn contains() {
+        let mut s = Stack::<i32, 3>::new();
+        assert_eq!(s.contains(&1), false);
+        assert_eq!(s.push(1), Ok(()));
+        assert_eq!(s.push(2), Ok(()));
+        assert_eq!(s.contains(&1), true);
+        assert_eq!(s.contains(&2), true);
+        assert_eq!(s.contains(&3), false);
+    }
+
+    #[test]
+    fn into_array() {
+        let s = Stack::<i32, 3>::try_from_iter([1, 2, 3]).unwrap();
+        assert_eq!(s.into_array(), Ok([1, 2, 3]));
+
+        let s = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        let s = s.into_array().unwrap_err();
+        assert_eq!(s.as_slice(), &[1, 2]);
+
+        let drops = Cell::new(0);
+        let s = Stack::<DropCounter, 2>::try_from_iter([DropCounter(&drops), DropCounter(&drops)]);
+        let array = s.unwrap().into_array().ok().unwrap();
+        assert_eq!(drops.get(), 0);
+        drop(array);
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn hash() {
+        fn hash_of(s: &Stack<i32, 3>) -> u64 {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            s.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let s1 = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        let s2 = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        let s3 = Stack::<i32, 3>::try_from_iter([2, 1]).unwrap();
+        assert_eq!(hash_of(&s1), hash_of(&s2));
+        assert_ne!(hash_of(&s1), hash_of(&s3));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let s = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        let json = serde_json::to_string(&s).unwrap();
+        assert_eq!(json, "[1,2]");
+        let s2: Stack<i32, 3> = serde_json::from_str(&json).unwrap();
+        assert_eq!(s, s2);
+
+        assert!(serde_json::from_str::<Stack<i32, 3>>("[1,2,3]").is_ok());
+        assert!(serde_json::from_str::<Stack<i32, 3>>("[1,2,3,4]").is_err());
+        let e = serde_json::from_str::<Stack<i32, 3>>("[1,2,3,4,5,6]").unwrap_err();
+        assert_eq!(
+            e.to_string()
+                .starts_with("invalid length 6, expected a sequence of at most 3"),
+            true
+        );
+        assert!(serde_json::from_str::<Stack<i32, 3>>("[1,\"a\"]").is_err());
+    }
+
+    #[test]
+    fn try_push() {
+        let mut s = Stack::<std::string::String, 1>::new();
+        assert_eq!(s.try_push("a".into()), Ok(()));
+        let b = std::string::String::from("b");
+        let b_ptr = b.as_ptr();
+        let returned = s.try_push(b).unwrap_err();
+        assert_eq!(returned, "b");
+        assert_eq!(returned.as_ptr(), b_ptr);
+        assert_eq!(s.as_slice(), &["a"]);
+    }
+
+    #[test]
+    fn push_saturating() {
+        let drops = Cell::new(0);
+        let mut s = Stack::<DropCounter, 2>::new();
+        assert_eq!(s.push_saturating(DropCounter(&drops)), true);
+        assert_eq!(s.push_saturating(DropCounter(&drops)), true);
+        assert_eq!(s.push_saturating(DropCounter(&drops)), false);
+        assert_eq!(drops.get(), 1);
+        assert_eq!(s.is_full(), true);
+        assert_eq!(s.size(), 2);
+    }
+
+    #[test]
+    fn binary_search() {
+        let mut s = Stack::<i32, 5>::new();
+        assert_eq!(s.binary_search(&1), Err(0));
+        assert_eq!(s.insert_sorted(5), Ok(0));
+        assert_eq!(s.insert_sorted(1), Ok(0));
+        assert_eq!(s.insert_sorted(3), Ok(1));
+        assert_eq!(s.insert_sorted(9), Ok(3));
+        assert_eq!(s.as_slice(), &[1, 3, 5, 9]);
+
+        assert_eq!(s.binary_search(&5), Ok(2));
+        assert_eq!(s.binary_search(&4), Err(2));
+        assert_eq!(s.binary_search(&10), Err(4));
+
+        assert_eq!(s.insert_sorted(4), Ok(2));
+        assert_eq!(s.insert_sorted(2), Err(Error::Full));
+        assert_eq!(s.as_slice(), &[1, 3, 4, 5, 9]);
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut s = Stack::<i32, 5>::new();
+        assert_eq!(s.insert(0, 2), Ok(()));
+        assert_eq!(s.insert(0, 1), Ok(()));
+        assert_eq!(s.insert(2, 4), Ok(()));
+        assert_eq!(s.insert(2, 3), Ok(()));
+        assert_eq!(s.insert(4, 5), Ok(()));
+        assert_eq!(s.as_slice(), &[1, 2, 3, 4, 5]);
+        assert_eq!(s.insert(0, 0), Err(Error::Full));
+
+        assert_eq!(s.remove(5), None);
+        assert_eq!(s.remove(0), Some(1));
+        assert_eq!(s.as_slice(), &[2, 3, 4, 5]);
+        assert_eq!(s.remove(1), Some(3));
+        assert_eq!(s.as_slice(), &[2, 4, 5]);
+        assert_eq!(s.remove(2), Some(5));
+        assert_eq!(s.as_slice(), &[2, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_out_of_range() {
+        let mut s = Stack::<i32, 3>::new();
+        let _ = s.insert(1, 1);
+    }
+
+    #[test]
+    fn dedup() {
+        let mut s = Stack::<i32, 5>::try_from_iter([1, 2, 3]).unwrap();
+        s.dedup();
+        assert_eq!(s.as_slice(), &[1, 2, 3]);
+
+        let mut s = Stack::<i32, 5>::try_from_iter([4, 4, 4, 4]).unwrap();
+        s.dedup();
+        assert_eq!(s.as_slice(), &[4]);
+
+        let mut s = Stack::<i32, 5>::try_from_iter([1, 1, 2, 1, 1]).unwrap();
+        s.dedup();
+        assert_eq!(s.as_slice(), &[1, 2, 1]);
+
+        let mut s = Stack::<i32, 5>::new();
+        s.dedup();
+        assert_eq!(s.is_empty(), true);
+
+        #[derive(PartialEq)]
+        struct Counted<'a>(i32, &'a Cell<usize>);
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let mut s = Stack::<Counted, 5>::new();
+        for v in [1, 1, 2, 2, 2] {
+            assert!(s.push(Counted(v, &drops)).is_ok());
+        }
+        s.dedup();
+        assert_eq!(drops.get(), 3);
+        assert_eq!(s.size(), 2);
+        assert_eq!((s[0].0, s[1].0), (1, 2));
+        drop(s);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn swap_remove() {
+        let mut s = Stack::<i32, 5>::try_from_iter([1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(s.swap_remove(5), None);
+        assert_eq!(s.swap_remove(4), Some(5));
+        assert_eq!(s.as_slice(), &[1, 2, 3, 4]);
+        assert_eq!(s.swap_remove(0), Some(1));
+        assert_eq!(s.as_slice(), &[4, 2, 3]);
+        assert_eq!(s.swap_remove(1), Some(2));
+        assert_eq!(s.as_slice(), &[4, 3]);
+        assert_eq!(s.swap_remove(1), Some(3));
+        assert_eq!(s.swap_remove(0), Some(4));
+        assert_eq!(s.swap_remove(0), None);
+    }
+
+    #[test]
+    fn reverse() {
+        let mut s = Stack::<i32, 3>::new();
+        s.reverse();
+        assert_eq!(s.as_slice(), &[] as &[i32]);
+        assert_eq!(s.push(1), Ok(()));
+        s.reverse();
+        assert_eq!(s.as_slice(), &[1]);
+        assert_eq!(s.push(2), Ok(()));
+        assert_eq!(s.push(3), Ok(()));
+        s.reverse();
+        assert_eq!(s.as_slice(), &[3, 2, 1]);
+        assert_eq!(s.pop(), Some(1));
+    }
+
+    #[test]
+    fn index() {
+        let mut s = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        assert_eq!(s[0], 1);
+        assert_eq!(s[1], 2);
+        s[0] = 10;
+        assert_eq!(s.get(0), Some(&10));
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_range() {
+        let s = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        let _ = s[2];
+    }
+
+    #[test]
+    fn split_off() {
+        let mut s1 = Stack::<i32, 4>::try_from_iter([1, 2, 3, 4]).unwrap();
+        let s2 = s1.split_off(1);
+        assert_eq!(s1.as_slice(), &[1]);
+        assert_eq!(s2.as_slice(), &[2, 3, 4]);
+        let s3 = s1.split_off(1);
+        assert_eq!(s1.as_slice(), &[1]);
+        assert_eq!(s3.is_empty(), true);
+        let s4 = s1.split_off(0);
+        assert_eq!(s1.is_empty(), true);
+        assert_eq!(s4.as_slice(), &[1]);
+
+        let drops = Cell::new(0);
+        let mut s1 = Stack::<DropCounter, 4>::new();
+        for _ in 0..4 {
+            assert!(s1.push(DropCounter(&drops)).is_ok());
+        }
+        let s2 = s1.split_off(2);
+        assert_eq!(drops.get(), 0);
+        drop(s1);
+        assert_eq!(drops.get(), 2);
+        drop(s2);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_off_out_of_range() {
+        let mut s = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        s.split_off(3);
+    }
+
+    #[test]
+    fn append() {
+        let mut s1 = Stack::<i32, 4>::try_from_iter([1, 2]).unwrap();
+        let mut s2 = Stack::<i32, 2>::try_from_iter([3, 4]).unwrap();
+        assert_eq!(s1.append(&mut s2), Ok(()));
+        assert_eq!(s1.as_slice(), &[1, 2, 3, 4]);
+        assert_eq!(s2.is_empty(), true);
+
+        let mut s3 = Stack::<i32, 2>::try_from_iter([5]).unwrap();
+        assert_eq!(s1.append(&mut s3), Err(Error::Full));
+        assert_eq!(s1.as_slice(), &[1, 2, 3, 4]);
+        assert_eq!(s3.as_slice(), &[5]);
+
+        let drops = Cell::new(0);
+        let mut s1 = Stack::<DropCounter, 4>::try_from_iter([DropCounter(&drops)]).unwrap();
+        let mut s2 = Stack::<DropCounter, 4>::try_from_iter([DropCounter(&drops)]).unwrap();
+        assert!(s1.append(&mut s2).is_ok());
+        drop(s2);
+        assert_eq!(drops.get(), 0);
+        drop(s1);
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn pop_if() {
+        let mut s = Stack::<i32, 3>::try_from_iter([1, 2]).unwrap();
+        assert_eq!(s.pop_if(|v| *v == 1), None);
+        assert_eq!(s.as_slice(), &[1, 2]);
+        assert_eq!(s.pop_if(|v| *v == 2), Some(2));
+        assert_eq!(s.pop_if(|v| *v == 1), Some(1));
+        assert_eq!(s.pop_if(|_| true), None);
+    }
+
+    #[test]
+    fn capacity_const() {
+        const CAPACITY: usize = Stack::<i32, 3>::CAPACITY;
+        let array = [0u8; Stack::<i32, 3>::CAPACITY];
+        assert_eq!(CAPACITY, 3);
+        assert_eq!(array.len(), Stack::<i32, 3>::new().capacity());
+    }
+
+    #[test]
+    fn fill() {
+        let mut s = Stack::<i32, 3>::try_from_iter([1]).unwrap();
+        s.fill(7);
+        assert_eq!(s.is_full(), true);
+        assert_eq!(s.as_slice(), &[1, 7, 7]);
+        s.fill(8);
+        assert_eq!(s.as_slice(), &[1, 7, 7]);
+
+        let mut s = Stack::<i32, 3>::new();
+        s.fill(5);
+        assert!(s.iter().all(|v| *v == 5));
+        assert_eq!(s.size(), 3);
+    }
+
+    #[test]
+    fn small_stack() {
+        let s = SmallStack::<u8>::default();
+        assert_eq!(s.capacity(), 16);
+        assert_eq!(SmallStack::<u8>::CAPACITY, 16);
+        assert_eq!(s.is_empty(), true);
+    }
+
+    #[test]
+    fn has_room_for() {
+        let mut s = Stack::<i32, 3>::new();
+        assert_eq!(s.has_room_for(0), true);
+        assert_eq!(s.has_room_for(3), true);
+        assert_eq!(s.has_room_for(4), false);
+        assert_eq!(s.push(1), Ok(()));
+        assert_eq!(s.has_room_for(2), true);
+        assert_eq!(s.has_room_for(3), false);
+        assert_eq!(s.has_room_for(usize::MAX), false);
+
+        s.fill(1);
+        assert_eq!(s.has_room_for(0), true);
+        assert_eq!(s.has_room_for(1), false);
+    }
+
+    #[test]
+    fn try_pop() {
+        let mut s = Stack::<i32, 3>::new();
+        assert_eq!(s.try_pop(), Err(Error::Empty));
+        assert_eq!(s.push(1), Ok(()));
+        assert_eq!(s.try_pop(), Ok(1));
+        assert_eq!(s.try_pop(), Err(Error::Empty));
+    }
+
+    #[test]
+    fn raw_parts() {
+        let mut s = Stack::<i32, 4>::try_from_iter([1, 2, 3]).unwrap();
+        let ptr = s.as_ptr();
+        // SAFETY: ptr is valid for s.size() elements
+        assert_eq!(unsafe { slice::from_raw_parts(ptr, s.size()) }, &[1, 2, 3]);
+
+        // SAFETY: See above
+        unsafe { *s.as_mut_ptr().add(1) = 5 };
+        assert_eq!(s.as_slice(), &[1, 5, 3]);
+
+        let mut buf = [const { MaybeUninit::uninit() }; 4];
+        buf[0].write(7);
+        buf[1].write(8);
+        // SAFETY: buf[0..2] is initialized
+        let s = unsafe { Stack::<i32, 4>::from_raw_parts(buf, 2) };
+        assert_eq!(s.as_slice(), &[7, 8]);
+    }
+
     #[test]
     fn stack() {
         let mut s = Stack::<i32, 3>::new();
@@ -138,8 +1199,8 @@ mod tests {
         assert_eq!(s.capacity(), 3);
         assert_eq!(s.is_full(), false);
         assert_eq!(s.is_empty(), true);
-        assert_eq!(s.as_slice(), &[]);
-        assert_eq!(s.as_mut_slice(), &[]);
+        assert_eq!(s.as_slice(), &[] as &[i32]);
+        assert_eq!(s.as_mut_slice(), &[] as &[i32]);
 
         assert_eq!(s.pop(), None);
 
@@ -161,8 +1222,8 @@ mod tests {
         assert_eq!(s.pop(), Some(2));
         assert_eq!(s.peek(), Some(&1));
         assert_eq!(s.pop(), Some(1));
-        assert_eq!(s.as_slice(), &[]);
-        assert_eq!(s.as_mut_slice(), &[]);
+        assert_eq!(s.as_slice(), &[] as &[i32]);
+        assert_eq!(s.as_mut_slice(), &[] as &[i32]);
 
         assert_eq!(s.push(4), Ok(()));
         assert_eq!(s.peek(), Some(&4));
@@ -187,8 +1248,8 @@ mod tests {
         assert_eq!(s.as_mut_slice(), &[4]);
         assert_eq!(s.pop(), Some(4));
         assert_eq!(s.peek(), None);
-        assert_eq!(s.as_slice(), &[]);
-        assert_eq!(s.as_mut_slice(), &[]);
+        assert_eq!(s.as_slice(), &[] as &[i32]);
+        assert_eq!(s.as_mut_slice(), &[] as &[i32]);
 
         assert_eq!(s.pop(), None);
         assert_eq!(s.size(), 0);
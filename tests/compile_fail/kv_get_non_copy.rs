@@ -0,0 +1,9 @@
+use {
+    hds::{Kv, StaticDataStore},
+    std::collections::hash_map::DefaultHasher,
+};
+
+fn main() {
+    let mut kv = Kv::with_hasher_and_store(DefaultHasher::new(), StaticDataStore::<128>::new());
+    let _ = kv.get::<String>("a");
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "defmt")]
+
+use {
+    hds::{DataStoreError, Error, KvError, Queue, Stack},
+    std::sync::Mutex,
+};
+
+static OUTPUT: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// Collects the encoded frames, the strings are interned so only the arguments are readable
+#[defmt::global_logger]
+struct Logger;
+
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {}
+    unsafe fn flush() {}
+    unsafe fn release() {}
+    unsafe fn write(bytes: &[u8]) {
+        OUTPUT.lock().unwrap().extend_from_slice(bytes);
+    }
+}
+
+defmt::timestamp!("");
+
+fn encode(f: impl FnOnce()) -> Vec<u8> {
+    OUTPUT.lock().unwrap().clear();
+    f();
+    OUTPUT.lock().unwrap().clone()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn format() {
+    assert_ne!(
+        encode(|| defmt::println!("{}", Error::Full)),
+        encode(|| defmt::println!("{}", Error::Empty))
+    );
+    assert!(
+        !encode(|| defmt::println!("{}", KvError::Store(DataStoreError::OutOfMemory))).is_empty()
+    );
+
+    let mut stack = Stack::<u32, 4>::new();
+    assert!(stack.push(0xdead_beef).is_ok());
+    assert!(stack.push(0xcafe_babe).is_ok());
+    let out = encode(|| defmt::println!("{}", stack));
+    assert!(contains(
+        &out,
+        &[0xef, 0xbe, 0xad, 0xde, 0xbe, 0xba, 0xfe, 0xca]
+    ));
+
+    // Wrap around, so the initialized slots aren't contiguous
+    let mut queue = Queue::<u32, 3>::new();
+    for v in [0x0bad_f00d, 2, 3] {
+        assert!(queue.push(v).is_ok());
+    }
+    assert_eq!(queue.pop(), Some(0x0bad_f00d));
+    assert!(queue.push(0x1234_5678).is_ok());
+    let out = encode(|| defmt::println!("{}", queue));
+    assert!(contains(&out, &2u32.to_le_bytes()));
+    assert!(contains(&out, &3u32.to_le_bytes()));
+    assert!(contains(&out, &0x1234_5678u32.to_le_bytes()));
+    assert!(!contains(&out, &0x0bad_f00du32.to_le_bytes()));
+}